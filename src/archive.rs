@@ -0,0 +1,579 @@
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use binrw::BinRead;
+use memchr::memmem;
+use memmap2::Mmap;
+
+use crate::{crypto, pyc, zip, zlib_decompress};
+use crate::error::{self, Error, Result};
+use crate::pyc::PythonVersion;
+use crate::pyz::Pyz;
+use crate::runtime::RuntimeOption;
+
+pub const ARCHIVE_ITEM_BINARY: u8          = b'b'; // binary
+pub const ARCHIVE_ITEM_DEPENDENCY: u8      = b'd'; // multipackage dependency
+pub const ARCHIVE_ITEM_PYZ: u8             = b'z'; // zlib (pyz) - frozen Python code
+pub const ARCHIVE_ITEM_ZIPFILE: u8         = b'Z'; // zlib (pyz) - frozen Python code
+pub const ARCHIVE_ITEM_PYPACKAGE: u8       = b'M'; // Python package (__init__.py)
+pub const ARCHIVE_ITEM_PYMODULE: u8        = b'm'; // Python module
+pub const ARCHIVE_ITEM_PYSOURCE: u8        = b's'; // Python script (v3)
+pub const ARCHIVE_ITEM_DATA: u8            = b'x'; // data
+pub const ARCHIVE_ITEM_RUNTIME_OPTION: u8  = b'o'; // runtime option
+pub const ARCHIVE_ITEM_SPLASH: u8          = b'l'; // splash resources
+pub const ARCHIVE_ITEM_SYMLINK: u8         = b'n'; // symbolic link
+
+pub const PYINST_MAGIC_BASE: [u8; 8] = [
+    b'M', b'E', b'I', 0x0C,
+    0x0B, 0x0A, 0x0B, 0x0E
+];
+
+const PYLIB_NAME_SIZE: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookieLayout {
+    /// PyInstaller 2.0: magic, package size, toc offset, toc size, python version
+    V20,
+    /// PyInstaller 2.1+: the same fields followed by the 64 byte python library name
+    V21
+}
+
+impl CookieLayout {
+    pub const fn size(self) -> usize {
+        match self {
+            CookieLayout::V20 => 24,
+            CookieLayout::V21 => 24 + PYLIB_NAME_SIZE,
+        }
+    }
+}
+
+/// The cookie at the end of the CArchive
+#[derive(BinRead, Debug, Clone)]
+#[br(big)]
+pub struct PyinstHeader {
+    pub signature: [u8; 8],
+    pub package_size: u32,
+    pub toc_offset: u32,
+    pub toc_size: u32,
+    pub python_version: u32,
+
+    #[br(ignore)]
+    pub pylib_name: Option<String>,
+    #[br(calc = CookieLayout::V20)]
+    pub layout: CookieLayout
+}
+
+impl PyinstHeader {
+    /// Start of the CArchive, every TOC offset is relative to it
+    pub fn overlay_offset(&self, cookie_offset: usize) -> usize {
+        (cookie_offset + self.layout.size()).saturating_sub(self.package_size as usize)
+    }
+
+    pub fn python_version(&self) -> Option<PythonVersion> {
+        PythonVersion::from_cookie(self.python_version)
+    }
+}
+
+/// An entry of the CArchive table of contents, `offset` is absolute in the file
+#[derive(Default, Debug, Clone)]
+pub struct PyinstEntry {
+    pub size: u32,
+    pub offset: u64,
+    pub compressed_size: u32,
+    pub uncompressed_size: u32,
+    pub compression_flag: u8,
+    pub type_: u8,
+    pub name: String
+}
+
+impl PyinstEntry {
+    pub fn is_compressed(&self) -> bool {
+        self.compression_flag == 1
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.type_ {
+            ARCHIVE_ITEM_BINARY => "binary",
+            ARCHIVE_ITEM_DEPENDENCY => "dependency",
+            ARCHIVE_ITEM_PYZ => "pyz",
+            ARCHIVE_ITEM_ZIPFILE => "zipfile",
+            ARCHIVE_ITEM_PYPACKAGE => "package",
+            ARCHIVE_ITEM_PYMODULE => "module",
+            ARCHIVE_ITEM_PYSOURCE => "script",
+            ARCHIVE_ITEM_DATA => "data",
+            ARCHIVE_ITEM_RUNTIME_OPTION => "option",
+            ARCHIVE_ITEM_SPLASH => "splash",
+            ARCHIVE_ITEM_SYMLINK => "symlink",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Where the pyc magic used for the headers was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MagicSource {
+    Pyz,
+    BaseLibrary,
+    CookieVersion,
+    PythonLibrary
+}
+
+impl MagicSource {
+    pub fn describe(self) -> &'static str {
+        match self {
+            MagicSource::Pyz => "PYZ header",
+            MagicSource::BaseLibrary => "base_library.zip",
+            MagicSource::CookieVersion => "cookie python version",
+            MagicSource::PythonLibrary => "bundled python library",
+        }
+    }
+}
+
+pub(crate) const ENTRY_FIXED_SIZE: usize = 4 * 4 + 1 + 1;
+
+pub(crate) fn parse_entry(data: &[u8], pos: usize, overlay_offset: usize) -> Result<PyinstEntry> {
+
+    // not using binrw cause idk how to parse null-terminated dynamic sized strings
+
+    let bad_toc = |reason: &str| Error::BadToc { offset: pos, reason: reason.to_string() };
+
+    let buffer = error::slice(data, pos, ENTRY_FIXED_SIZE).map_err(|_| bad_toc("truncated entry"))?;
+
+    let size = u32::from_be_bytes(buffer[0..4].try_into().unwrap());
+    // The stored offset is relative to the package, which can start past 4 GiB
+    let offset = (u32::from_be_bytes(buffer[4..8].try_into().unwrap()) as u64)
+        .checked_add(overlay_offset as u64)
+        .ok_or_else(|| bad_toc("offset overflows"))?;
+    let compressed_size = u32::from_be_bytes(buffer[8..12].try_into().unwrap());
+    let uncompressed_size = u32::from_be_bytes(buffer[12..16].try_into().unwrap());
+    let compression_flag = buffer[16];
+    let type_ = buffer[17];
+
+    // name_size = TotalSize - ((Size) Size + (Offset) Size + (CompressedSize) Size + (UncompressedSize) Size + (CompressionFlag) Size + (type) Size)
+    let name_size = (size as usize).checked_sub(ENTRY_FIXED_SIZE).ok_or_else(|| bad_toc("entry size smaller than its header"))?;
+
+    let mut buffer = error::slice(data, pos + ENTRY_FIXED_SIZE, name_size)
+        .map_err(|_| bad_toc("truncated name"))?;
+
+    if let Some(pos) = buffer.iter().position(|&b| b == 0) {
+        buffer = &buffer[..pos];
+    }
+
+    // Names are utf-8, a sample with anything else should still be extractable
+    let mut name = String::from_utf8_lossy(buffer).into_owned();
+
+    if type_ == ARCHIVE_ITEM_PYSOURCE {
+        name.push_str(".pyc");
+    }
+
+    Ok(PyinstEntry {
+        size,
+        offset,
+        compressed_size,
+        uncompressed_size,
+        compression_flag,
+        type_,
+        name
+    })
+
+}
+
+// The 2.1+ layout is told apart by the python library name that follows the 2.0 fields
+fn parse_header(data: &[u8], header_offset: usize) -> Result<PyinstHeader> {
+
+    let mut cursor = Cursor::new(&data[header_offset..]);
+    let mut header = PyinstHeader::read(&mut cursor).map_err(|_| Error::TruncatedCookie { offset: header_offset })?;
+
+    let mut pylib_name = [0u8; PYLIB_NAME_SIZE];
+    if cursor.read_exact(&mut pylib_name).is_ok() && pylib_name.to_ascii_lowercase().windows(6).any(|w| w == b"python") {
+        let len = pylib_name.iter().position(|&b| b == 0).unwrap_or(PYLIB_NAME_SIZE);
+        header.pylib_name = Some(String::from_utf8_lossy(&pylib_name[..len]).into_owned());
+        header.layout = CookieLayout::V21;
+    }
+
+    Ok(header)
+}
+
+/// Offsets of every cookie magic in the file, starting from the end. The bootloader and
+/// wrapped installers may contain the magic too, so each candidate has to be validated
+pub fn find_cookies(data: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    memmem::rfind_iter(data, &PYINST_MAGIC_BASE)
+}
+
+// Parses the cookie at `cookie_offset` and its TOC, the package and the TOC have to lie before the cookie
+fn parse_package(data: &[u8], cookie_offset: usize) -> Result<(PyinstHeader, usize, Vec<PyinstEntry>)> {
+    let header = parse_header(data, cookie_offset)?;
+
+    let invalid = |reason: &str| Error::InvalidCookie { offset: cookie_offset, reason: reason.to_string() };
+
+    let cookie_end = cookie_offset + header.layout.size();
+    if header.package_size as usize > cookie_end {
+        return Err(invalid("package larger than the file"));
+    }
+
+    // Anything after the cookie (signatures, appended data) is not part of the package
+    let overlay_offset = header.overlay_offset(cookie_offset);
+
+    let toc_start = overlay_offset + header.toc_offset as usize;
+    if toc_start.checked_add(header.toc_size as usize).is_none_or(|toc_end| toc_end > cookie_offset) {
+        return Err(invalid("toc outside of the package"));
+    }
+
+    let mut pos = toc_start;
+    let mut bytes_read: u64 = 0;
+    let mut entries = Vec::new();
+
+    while bytes_read < header.toc_size as u64 {
+        let entry = parse_entry(data, pos, overlay_offset)?;
+
+        pos += entry.size as usize;
+        bytes_read += entry.size as u64;
+        entries.push(entry);
+    }
+
+    Ok((header, overlay_offset, entries))
+}
+
+// A cookie whose magic was overwritten is recognized by its fields alone: a known python version,
+// a package and a TOC that fit before it, and a TOC whose entries all have a known type and lie in the package
+fn plausible_package(data: &[u8], cookie_offset: usize) -> Option<(PyinstHeader, usize, Vec<PyinstEntry>)> {
+    let field = |index: usize| u32::from_be_bytes(data[cookie_offset + 8 + index * 4..][..4].try_into().unwrap()) as usize;
+    let (package_size, toc_offset, toc_size, python_version) = (field(0), field(1), field(2), field(3));
+
+    if PythonVersion::from_cookie(python_version as u32).is_none()
+        || toc_size < ENTRY_FIXED_SIZE
+        || package_size > cookie_offset + CookieLayout::V21.size()
+        || toc_offset.saturating_add(toc_size) > package_size {
+        return None;
+    }
+
+    let (header, overlay_offset, entries) = parse_package(data, cookie_offset).ok()?;
+
+    let sane = |entry: &PyinstEntry| {
+        !entry.name.is_empty()
+            && entry.type_name() != "unknown"
+            && entry.offset >= overlay_offset as u64
+            && entry.offset + entry.compressed_size as u64 <= cookie_offset as u64
+    };
+
+    (!entries.is_empty() && entries.iter().all(sane)).then_some((header, overlay_offset, entries))
+}
+
+// Cookies with a modified magic are only looked for this far before the places PyInstaller writes
+// the package end: a scan of the whole file takes far too long on large files that are no archive
+const RECOVERY_WINDOW: usize = 1 << 20;
+
+fn read_uint(data: &[u8], pos: usize, size: usize, big_endian: bool) -> Option<usize> {
+    let bytes = data.get(pos..pos.checked_add(size)?)?;
+    let fold = |value: u64, &b: &u8| (value << 8) | b as u64;
+    let value = if big_endian { bytes.iter().fold(0, fold) } else { bytes.iter().rev().fold(0, fold) };
+    usize::try_from(value).ok()
+}
+
+// Start of the Authenticode signature of a PE, appended after the overlay
+fn pe_certificate_offset(data: &[u8]) -> Option<usize> {
+    if !data.starts_with(b"MZ") {
+        return None;
+    }
+    let pe = read_uint(data, 0x3C, 4, false)?;
+    if data.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+
+    // The data directories follow the optional header fields, the certificate table is the 5th
+    let optional_header = pe + 24;
+    let directories = match read_uint(data, optional_header, 2, false)? {
+        0x10B => optional_header + 96,
+        0x20B => optional_header + 112,
+        _ => return None,
+    };
+    let offset = read_uint(data, directories + 4 * 8, 4, false)?;
+    let size = read_uint(data, directories + 4 * 8 + 4, 4, false)?;
+
+    (size > 0 && offset <= data.len()).then_some(offset)
+}
+
+// End of the `pydata` section the package is stored in by PyInstaller on Linux
+fn elf_package_end(data: &[u8]) -> Option<usize> {
+    if !data.starts_with(b"\x7fELF") {
+        return None;
+    }
+    let big_endian = *data.get(5)? == 2;
+    let (wide, fields) = match data.get(4)? {
+        1 => (4, [0x20, 0x2E, 0x30, 0x32]),
+        2 => (8, [0x28, 0x3A, 0x3C, 0x3E]),
+        _ => return None,
+    };
+    let [section_headers, entry_size, count, names_index] = fields;
+
+    let section_headers = read_uint(data, section_headers, wide, big_endian)?;
+    let entry_size = read_uint(data, entry_size, 2, big_endian)?;
+    let count = read_uint(data, count, 2, big_endian)?;
+    let names_index = read_uint(data, names_index, 2, big_endian)?;
+
+    // sh_name, then sh_offset and sh_size after the type, flags and address fields
+    let section = |index: usize| -> Option<(usize, usize, usize)> {
+        let header = section_headers.checked_add(index.checked_mul(entry_size)?)?;
+        let name = read_uint(data, header, 4, big_endian)?;
+        let offset = read_uint(data, header + 8 + 2 * wide, wide, big_endian)?;
+        let size = read_uint(data, header + 8 + 3 * wide, wide, big_endian)?;
+        Some((name, offset, size))
+    };
+
+    let (_, names_offset, _) = section(names_index)?;
+    (0..count).filter_map(section).find_map(|(name, offset, size)| {
+        let name = data.get(names_offset.checked_add(name)?..)?;
+        name.starts_with(b"pydata\0").then(|| offset.checked_add(size)).flatten()
+    })
+}
+
+/// Offset of the last structurally valid cookie, whatever its magic, for samples that overwrite it.
+/// Only the end of the file, of the PE overlay and of the ELF `pydata` section are searched
+pub fn recover_cookie(data: &[u8]) -> Option<usize> {
+    let mut ends = vec![data.len()];
+    ends.extend(pe_certificate_offset(data));
+    ends.extend(elf_package_end(data).filter(|&end| end <= data.len()));
+
+    ends.into_iter()
+        .filter_map(|end| {
+            let last = end.checked_sub(CookieLayout::V20.size())?;
+            (last.saturating_sub(RECOVERY_WINDOW)..=last).rev()
+                .find(|&cookie_offset| plausible_package(data, cookie_offset).is_some())
+        })
+        .max()
+}
+
+enum Source<'a> {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+    Borrowed(&'a [u8])
+}
+
+/// A parsed PyInstaller CArchive
+pub struct Archive<'a> {
+    source: Source<'a>,
+    cookie_offset: usize,
+    overlay_offset: usize,
+    header: PyinstHeader,
+    entries: Vec<PyinstEntry>
+}
+
+impl Archive<'static> {
+    /// Maps the file at `path` and parses it
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let fp = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&fp)? };
+
+        Archive::parse(Source::Mapped(mmap))
+    }
+
+    /// Reads the whole stream in memory and parses it
+    pub fn from_reader<R: Read + Seek>(mut reader: R) -> Result<Self> {
+        let mut data = Vec::new();
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_to_end(&mut data)?;
+
+        Archive::parse(Source::Owned(data))
+    }
+}
+
+impl<'a> Archive<'a> {
+    /// Parses an archive already in memory without copying it
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self> {
+        Archive::parse(Source::Borrowed(data))
+    }
+
+    /// Every consistent archive in `data` ordered by offset, for files carrying several
+    /// concatenated packages. Cookies inside a package that was already found are skipped,
+    /// nested archives are stored as entries and reached through them
+    pub fn find_all(data: &'a [u8]) -> Vec<Self> {
+        let mut archives: Vec<Archive> = Vec::new();
+
+        for cookie_offset in find_cookies(data) {
+            if archives.iter().any(|archive| (archive.overlay_offset..archive.cookie_offset).contains(&cookie_offset)) {
+                continue;
+            }
+            if let Ok((header, overlay_offset, entries)) = parse_package(data, cookie_offset) {
+                archives.push(Archive { source: Source::Borrowed(data), cookie_offset, overlay_offset, header, entries });
+            }
+        }
+
+        archives.reverse();
+        archives
+    }
+
+    fn parse(source: Source<'a>) -> Result<Self> {
+        let data: &[u8] = match &source {
+            Source::Mapped(mmap) => mmap,
+            Source::Owned(data) => data,
+            Source::Borrowed(data) => data,
+        };
+
+        // The last consistent cookie wins, when there is none the error of the last candidate is kept
+        let mut error = None;
+        let mut package = None;
+
+        for cookie_offset in find_cookies(data) {
+            match parse_package(data, cookie_offset) {
+                Ok(parsed) => {
+                    package = Some((cookie_offset, parsed));
+                    break;
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        // Fall back to a cookie with a modified magic
+        if package.is_none() {
+            package = recover_cookie(data)
+                .and_then(|cookie_offset| Some((cookie_offset, plausible_package(data, cookie_offset)?)));
+        }
+
+        let Some((cookie_offset, (header, overlay_offset, entries))) = package else {
+            return Err(error.unwrap_or(Error::CookieNotFound));
+        };
+
+        Ok(Archive { source, cookie_offset, overlay_offset, header, entries })
+    }
+
+    /// The whole file the archive was parsed from
+    pub fn data(&self) -> &[u8] {
+        match &self.source {
+            Source::Mapped(mmap) => mmap,
+            Source::Owned(data) => data,
+            Source::Borrowed(data) => data,
+        }
+    }
+
+    pub fn header(&self) -> &PyinstHeader {
+        &self.header
+    }
+
+    pub fn cookie_offset(&self) -> usize {
+        self.cookie_offset
+    }
+
+    /// The cookie was recovered from its fields, its magic is not `PYINST_MAGIC_BASE`
+    pub fn magic_modified(&self) -> bool {
+        self.header.signature != PYINST_MAGIC_BASE
+    }
+
+    pub fn overlay_offset(&self) -> usize {
+        self.overlay_offset
+    }
+
+    /// Bytes after the cookie that are not part of the package
+    pub fn tail_size(&self) -> usize {
+        self.data().len() - self.cookie_offset - self.header.layout.size()
+    }
+
+    pub fn entries(&self) -> &[PyinstEntry] {
+        &self.entries
+    }
+
+    pub fn find(&self, name: &str) -> Option<&PyinstEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Stored bytes of an entry, still compressed when the compression flag is set
+    pub fn raw_entry(&self, entry: &PyinstEntry) -> Result<&[u8]> {
+        error::range(self.data(), entry.offset, entry.compressed_size as u64)
+    }
+
+    /// Decompressed contents of an entry
+    pub fn read_entry(&self, entry: &PyinstEntry) -> Result<Vec<u8>> {
+        let content = self.raw_entry(entry)?;
+
+        if entry.is_compressed() {
+            zlib_decompress(content, entry.uncompressed_size as usize)
+        } else {
+            Ok(content.to_vec())
+        }
+    }
+
+    /// Parses a PYZ entry, PYZ archives are stored uncompressed
+    pub fn pyz(&self, entry: &PyinstEntry) -> Result<Pyz<'_>> {
+        Pyz::parse(self.raw_entry(entry)?)
+    }
+
+    /// Magic of the pyc headers, taken from the PYZ header or inferred when there is no usable PYZ:
+    /// the magic of a pyc inside `base_library.zip` is exact, then the version from the cookie
+    /// or from the bundled python library maps to the release magic
+    pub fn pyc_magic(&self) -> Option<([u8; 4], MagicSource)> {
+        let from_pyz = self.entries.iter()
+            .filter(|entry| entry.type_ == ARCHIVE_ITEM_PYZ)
+            .filter_map(|entry| self.pyz(entry).ok())
+            .map(|pyz| pyz.header.version)
+            .find(|&magic| pyc::magic_number(magic).is_some());
+
+        if let Some(magic) = from_pyz {
+            return Some((magic, MagicSource::Pyz));
+        }
+
+        let from_base_library = || {
+            let entry = self.entries.iter().find(|entry| entry.name.ends_with("base_library.zip"))?;
+            let data = self.read_entry(entry).ok()?;
+
+            zip::entries(&data).ok()?.iter()
+                .filter(|zip_entry| zip_entry.name.ends_with(".pyc"))
+                .find_map(|zip_entry| {
+                    let pyc = zip::read(&data, zip_entry).ok()?;
+                    let magic: [u8; 4] = pyc.get(..4)?.try_into().ok()?;
+                    pyc::version_for_magic(magic).map(|_| magic)
+                })
+        };
+
+        if let Some(magic) = from_base_library() {
+            return Some((magic, MagicSource::BaseLibrary));
+        }
+
+        if let Some(magic) = self.header.python_version().and_then(pyc::magic_for_version) {
+            return Some((magic, MagicSource::CookieVersion));
+        }
+
+        self.header.pylib_name.iter()
+            .chain(self.entries.iter().map(|entry| &entry.name))
+            .find_map(|name| pyc::version_from_library_name(name))
+            .and_then(pyc::magic_for_version)
+            .map(|magic| (magic, MagicSource::PythonLibrary))
+    }
+
+    /// Full pyc header for the code entries, empty when the magic cannot be found
+    pub fn pyc_header(&self) -> Vec<u8> {
+        match self.pyc_magic() {
+            Some((magic, _)) => pyc::build_header(magic, self.header.python_version()),
+            None => Vec::new(),
+        }
+    }
+
+    /// Interpreter flags and bootloader settings given as runtime option entries
+    pub fn runtime_options(&self) -> Vec<RuntimeOption> {
+        self.entries.iter()
+            .filter(|entry| entry.type_ == ARCHIVE_ITEM_RUNTIME_OPTION)
+            .map(|entry| RuntimeOption::parse(&entry.name))
+            .collect()
+    }
+
+    /// Looks for the `pyimod00_crypto_key` module, stored in the CArchive by PyInstaller 4+ and in the PYZ by 3.x
+    pub fn crypto_key(&self) -> Option<String> {
+        let version = self.pyc_magic()
+            .and_then(|(magic, _)| pyc::version_for_magic(magic))
+            .or(self.header.python_version());
+
+        for entry in &self.entries {
+            if entry.name.trim_end_matches(".pyc") == crypto::CRYPTO_KEY_MODULE {
+                return crypto::find_key_in_code(&self.read_entry(entry).ok()?, version);
+            }
+
+            if entry.type_ == ARCHIVE_ITEM_PYZ && let Ok(pyz) = self.pyz(entry)
+                && let Some(member) = pyz.find(crypto::CRYPTO_KEY_MODULE)
+                && let Ok(code) = pyz.read_member(member, None) {
+                return crypto::find_key_in_code(&code, version);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,334 @@
+// Decryption of PYZ members built with PyInstaller's `--key` option (removed in 6.0)
+//
+// PyInstaller 3.x encrypts with pycrypto AES in CFB mode (8 bit segments) and 4.x-5.x with
+// tinyaes in CTR mode, both prefix every member with its 16 byte IV.
+
+use crate::marshal::{self, Value};
+use crate::pyc::PythonVersion;
+use crate::zlib_inflate;
+
+pub const CRYPT_BLOCK_SIZE: usize = 16;
+
+pub const CRYPTO_KEY_MODULE: &str = "pyimod00_crypto_key";
+
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+fn xtime(x: u8) -> u8 {
+    (x << 1) ^ if x & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+// Encrypt-only AES, CFB and CTR never need the inverse cipher
+#[derive(Clone)]
+struct Aes {
+    round_keys: Vec<[u8; 16]>,
+}
+
+impl Aes {
+    fn new(key: &[u8]) -> Option<Self> {
+        let nk = match key.len() {
+            16 => 4,
+            24 => 6,
+            32 => 8,
+            _ => return None,
+        };
+        let rounds = nk + 6;
+        let total = 4 * (rounds + 1);
+
+        let mut words: Vec<[u8; 4]> = key.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]).collect();
+        for i in nk..total {
+            let mut temp = words[i - 1];
+            if i % nk == 0 {
+                temp = [
+                    SBOX[temp[1] as usize] ^ RCON[i / nk - 1],
+                    SBOX[temp[2] as usize],
+                    SBOX[temp[3] as usize],
+                    SBOX[temp[0] as usize],
+                ];
+            } else if nk > 6 && i % nk == 4 {
+                temp = temp.map(|b| SBOX[b as usize]);
+            }
+            let prev = words[i - nk];
+            words.push([prev[0] ^ temp[0], prev[1] ^ temp[1], prev[2] ^ temp[2], prev[3] ^ temp[3]]);
+        }
+
+        let round_keys = words.chunks(4).map(|w| {
+            let mut block = [0u8; 16];
+            for (i, word) in w.iter().enumerate() {
+                block[i * 4..i * 4 + 4].copy_from_slice(word);
+            }
+            block
+        }).collect();
+
+        Some(Aes { round_keys })
+    }
+
+    fn encrypt_block(&self, input: &[u8; 16]) -> [u8; 16] {
+        let rounds = self.round_keys.len() - 1;
+        let mut state = *input;
+
+        for (s, k) in state.iter_mut().zip(&self.round_keys[0]) {
+            *s ^= k;
+        }
+
+        for round in 1..=rounds {
+            // SubBytes + ShiftRows, the state is column major
+            let mut shifted = [0u8; 16];
+            for col in 0..4 {
+                for row in 0..4 {
+                    shifted[col * 4 + row] = SBOX[state[((col + row) % 4) * 4 + row] as usize];
+                }
+            }
+            state = shifted;
+
+            if round != rounds {
+                for col in state.chunks_mut(4) {
+                    let (a0, a1, a2, a3) = (col[0], col[1], col[2], col[3]);
+                    let all = a0 ^ a1 ^ a2 ^ a3;
+                    col[0] ^= all ^ xtime(a0 ^ a1);
+                    col[1] ^= all ^ xtime(a1 ^ a2);
+                    col[2] ^= all ^ xtime(a2 ^ a3);
+                    col[3] ^= all ^ xtime(a3 ^ a0);
+                }
+            }
+
+            for (s, k) in state.iter_mut().zip(&self.round_keys[round]) {
+                *s ^= k;
+            }
+        }
+
+        state
+    }
+}
+
+// Keys are normalized the way PyInstaller does, `key.zfill(16)[:16]` on the encoded key: zero filled on
+// the left after a leading sign, then truncated, both counted in bytes
+fn normalize_key(key: &[u8]) -> Vec<u8> {
+    let sign = matches!(key.first(), Some(b'+' | b'-')) as usize;
+    let fill = CRYPT_BLOCK_SIZE.saturating_sub(key.len());
+
+    let mut normalized = key[..sign].to_vec();
+    normalized.resize(sign + fill, b'0');
+    normalized.extend_from_slice(&key[sign..]);
+    normalized.truncate(CRYPT_BLOCK_SIZE);
+    normalized
+}
+
+#[derive(Clone)]
+pub struct PyzCipher {
+    aes: Aes,
+}
+
+impl PyzCipher {
+    pub fn new(key: &str) -> Option<Self> {
+        Some(PyzCipher { aes: Aes::new(&normalize_key(key.as_bytes()))? })
+    }
+
+    // tinyaes, PyInstaller 4.x-5.x
+    fn decrypt_ctr(&self, iv: &[u8; 16], data: &[u8]) -> Vec<u8> {
+        let mut counter = *iv;
+        let mut output = Vec::with_capacity(data.len());
+
+        for chunk in data.chunks(CRYPT_BLOCK_SIZE) {
+            let keystream = self.aes.encrypt_block(&counter);
+            output.extend(chunk.iter().zip(keystream).map(|(c, k)| c ^ k));
+
+            for byte in counter.iter_mut().rev() {
+                *byte = byte.wrapping_add(1);
+                if *byte != 0 {
+                    break;
+                }
+            }
+        }
+
+        output
+    }
+
+    // pycrypto's default CFB mode, PyInstaller 3.x
+    fn decrypt_cfb8(&self, iv: &[u8; 16], data: &[u8]) -> Vec<u8> {
+        let mut register = *iv;
+        let mut output = Vec::with_capacity(data.len());
+
+        for &c in data {
+            let keystream = self.aes.encrypt_block(&register);
+            output.push(c ^ keystream[0]);
+
+            register.copy_within(1.., 0);
+            register[CRYPT_BLOCK_SIZE - 1] = c;
+        }
+
+        output
+    }
+
+    // Decrypts and decompresses a member, trying each mode until one yields a valid zlib stream
+    pub fn decrypt_and_inflate(&self, data: &[u8]) -> Option<Vec<u8>> {
+        if data.len() <= CRYPT_BLOCK_SIZE {
+            return None;
+        }
+
+        let (iv, payload) = data.split_at(CRYPT_BLOCK_SIZE);
+        let iv: &[u8; 16] = iv.try_into().unwrap();
+
+        zlib_inflate(&self.decrypt_ctr(iv, payload))
+            .or_else(|_| zlib_inflate(&self.decrypt_cfb8(iv, payload)))
+            .ok()
+    }
+}
+
+// Recovers the key from the `pyimod00_crypto_key` module code, which only holds `key = '<16 chars>'`.
+// The code object is decoded when the python version is known, else the first marshalled 16 char
+// printable string is taken.
+pub fn find_key_in_code(code: &[u8], version: Option<PythonVersion>) -> Option<String> {
+    let printable = |bytes: &[u8]| bytes.iter().all(|b| (0x20..0x7f).contains(b));
+
+    if let Some(version) = version && let Ok(Value::Code(module)) = marshal::loads_code(code, version) {
+        return module.consts.iter()
+            .filter_map(Value::as_str)
+            .find(|key| key.len() == CRYPT_BLOCK_SIZE && printable(key.as_bytes()));
+    }
+
+    for i in 0..code.len() {
+        let (len, start) = match code[i] & 0x7F {
+            b's' | b't' | b'u' | b'a' | b'A' => match code.get(i + 1..i + 5) {
+                Some(len) => (u32::from_le_bytes(len.try_into().unwrap()) as usize, i + 5),
+                None => continue,
+            },
+            b'z' | b'Z' => match code.get(i + 1) {
+                Some(&len) => (len as usize, i + 2),
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        if len != CRYPT_BLOCK_SIZE {
+            continue;
+        }
+
+        if let Some(candidate) = code.get(start..start + len) && printable(candidate) {
+            return Some(String::from_utf8_lossy(candidate).into_owned());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn cipher(key: &str) -> PyzCipher {
+        PyzCipher { aes: Aes::new(&hex(key)).unwrap() }
+    }
+
+    #[test]
+    fn keys_are_normalized_in_bytes() {
+        assert_eq!(normalize_key(b"key"), b"0000000000000key");
+        assert_eq!(normalize_key(b"-key"), b"-000000000000key");
+        assert_eq!(normalize_key(b"0123456789abcdefgh"), b"0123456789abcdef");
+        // "clé" and "é" * 9 encoded, as long as their bytes and not their chars
+        assert_eq!(normalize_key("clé".as_bytes()), b"000000000000cl\xc3\xa9");
+        assert_eq!(normalize_key("ééééééééé".as_bytes()), "éééééééé".as_bytes());
+        assert!(PyzCipher::new("ééééééééé").is_some());
+    }
+
+    // FIPS-197 appendix C
+    #[test]
+    fn aes_encrypts_the_fips_197_blocks() {
+        let plaintext: [u8; 16] = hex("00112233445566778899aabbccddeeff").try_into().unwrap();
+        let cases = [
+            ("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a"),
+            ("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191"),
+            ("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f", "8ea2b7ca516745bfeafc49904b496089"),
+        ];
+
+        for (key, ciphertext) in cases {
+            assert_eq!(Aes::new(&hex(key)).unwrap().encrypt_block(&plaintext).to_vec(), hex(ciphertext));
+        }
+        assert!(Aes::new(&[0; 15]).is_none());
+    }
+
+    // SP 800-38A F.5.2 CTR-AES128.Decrypt
+    #[test]
+    fn ctr_decrypts_the_sp_800_38a_vector() {
+        let cipher = cipher("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv = hex("f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").try_into().unwrap();
+        let ciphertext = hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+                              5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee");
+        let plaintext = hex("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710");
+
+        assert_eq!(cipher.decrypt_ctr(&iv, &ciphertext), plaintext);
+    }
+
+    // The counter is one 128 bit big endian integer, the carry crosses the 64 bit halves
+    #[test]
+    fn ctr_carries_across_the_whole_counter() {
+        let cipher = cipher("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv = hex("00000000000000ffffffffffffffffff").try_into().unwrap();
+        let keystream = hex("dacc9148febbffe342d5805537ea155ff644566de02f529aa57d9a6064ac0ab6");
+
+        assert_eq!(cipher.decrypt_ctr(&iv, &[0; 32]), keystream);
+    }
+
+    // SP 800-38A F.3.8 CFB8-AES128.Decrypt
+    #[test]
+    fn cfb8_decrypts_the_sp_800_38a_vector() {
+        let cipher = cipher("2b7e151628aed2a6abf7158809cf4f3c");
+        let iv = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+
+        assert_eq!(cipher.decrypt_cfb8(&iv, &hex("3b79424c9c0dd436bace9e0ed4586a4f32b9")), hex("6bc1bee22e409f96e93d7e117393172aae2d"));
+    }
+
+    // zlib.compress(b'import os\nprint(os.name)\n') encrypted by `openssl enc` with the key `secret`
+    // as PyInstaller pads it (`0000000000secret`), prefixed with the IV like a PYZ member
+    const IV: &str = "a1b2c3d4e5f60718293a4b5c6d7e8f90";
+    const TINYAES_MEMBER: &str = "4848f1ef5ab41b24b68f9c7645fab9d54baa9688ab65589c14abc57179307a440a";
+    const PYCRYPTO_MEMBER: &str = "48c791ea239dcd0fec0690253ffb180a56748168b7c3425397273f006ce73e052a";
+
+    #[test]
+    fn decrypts_tinyaes_and_pycrypto_members() {
+        let cipher = PyzCipher::new("secret").unwrap();
+
+        for member in [TINYAES_MEMBER, PYCRYPTO_MEMBER] {
+            let data = hex(&format!("{}{}", IV, member));
+            assert_eq!(cipher.decrypt_and_inflate(&data).unwrap(), b"import os\nprint(os.name)\n");
+        }
+
+        let data = hex(&format!("{}{}", IV, TINYAES_MEMBER));
+        assert!(PyzCipher::new("other").unwrap().decrypt_and_inflate(&data).is_none());
+        assert!(cipher.decrypt_and_inflate(&data[..CRYPT_BLOCK_SIZE]).is_none());
+    }
+
+    #[test]
+    fn keys_are_padded_and_truncated_to_16_chars() {
+        let data = hex(&format!("{}{}", IV, TINYAES_MEMBER));
+
+        assert!(PyzCipher::new("0000000000secret").unwrap().decrypt_and_inflate(&data).is_some());
+        assert_eq!(cipher("30303030303030303030736563726574").decrypt_ctr(&[0; 16], &[0; 16]),
+                   PyzCipher::new("secret").unwrap().decrypt_ctr(&[0; 16], &[0; 16]));
+        assert_eq!(PyzCipher::new("0123456789abcdefXYZ").unwrap().decrypt_ctr(&[0; 16], &[0; 16]),
+                   PyzCipher::new("0123456789abcdef").unwrap().decrypt_ctr(&[0; 16], &[0; 16]));
+    }
+}
@@ -0,0 +1,114 @@
+// Dependency entries ('d') of multipackage bundles: the file is not stored in this archive but in a
+// sibling executable, the entry name is `path:filename` with `path` relative to the executable directory
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The sibling executable, or its directory for onedir builds
+    pub path: String,
+    /// Name of the entry in the sibling archive
+    pub filename: String
+}
+
+impl Dependency {
+    // Split on the last `:` so a drive letter in the path is kept
+    pub fn parse(entry_name: &str) -> Option<Self> {
+        let (path, filename) = entry_name.rsplit_once(':')?;
+
+        if path.is_empty() || filename.is_empty() {
+            return None;
+        }
+
+        Some(Dependency { path: path.to_string(), filename: filename.to_string() })
+    }
+
+    /// File name of the sibling executable, used to match executables given by hand
+    pub fn executable_name(&self) -> &str {
+        self.path.rsplit(['/', '\\']).next().unwrap_or(&self.path)
+    }
+
+    /// The sibling executable: one of `candidates` with the same file name, else next to `input`.
+    /// The `.exe` suffix may be left out of the reference. The path comes from the sample, it must
+    /// stay inside the directory of `input`
+    pub fn resolve(&self, input: &Path, candidates: &[PathBuf]) -> Result<PathBuf, &'static str> {
+        let name = self.executable_name();
+        let names = [name.to_string(), format!("{}.exe", name)];
+
+        let given = candidates.iter()
+            .find(|candidate| candidate.file_name().is_some_and(|file_name| names.iter().any(|name| file_name == name.as_str())));
+        if let Some(given) = given {
+            return Ok(given.clone());
+        }
+
+        let relative = paths::sanitize(&self.path)?;
+        let mut executable = relative.clone().into_os_string();
+        executable.push(".exe");
+
+        let dir = input.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let path = [relative, PathBuf::from(executable)].into_iter()
+            .map(|path| dir.join(path))
+            .find(|path| path.is_file())
+            .ok_or("executable not found")?;
+
+        // The names are checked, a link in the directory may still point anywhere
+        let inside = fs::canonicalize(&path).ok()
+            .zip(fs::canonicalize(dir).ok())
+            .is_some_and(|(path, dir)| path.starts_with(dir));
+        if !inside {
+            return Err("executable outside of the input directory");
+        }
+
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dependency(path: &str) -> Dependency {
+        Dependency::parse(&format!("{}:lib/shared.so", path)).unwrap()
+    }
+
+    #[test]
+    fn parse_splits_on_the_last_colon() {
+        assert_eq!(dependency("app2"), Dependency { path: "app2".to_string(), filename: "lib/shared.so".to_string() });
+        assert_eq!(Dependency::parse("C:\\dist\\app2:x").unwrap().path, "C:\\dist\\app2");
+        assert_eq!(Dependency::parse(":x"), None);
+        assert_eq!(Dependency::parse("app2:"), None);
+        assert_eq!(dependency("..\\dist\\app2").executable_name(), "app2");
+    }
+
+    #[test]
+    fn resolve_stays_in_the_input_directory() {
+        let root = std::env::temp_dir().join(format!("extractor-dependency-{}", std::process::id()));
+        let dist = root.join("dist");
+        fs::create_dir_all(dist.join("sub")).unwrap();
+        for file in ["dist/app1.exe", "dist/app2.exe", "dist/sub/app3", "outside.exe"] {
+            fs::write(root.join(file), b"").unwrap();
+        }
+        let input = dist.join("app1.exe");
+
+        assert_eq!(dependency("app2").resolve(&input, &[]), Ok(dist.join("app2.exe")));
+        assert_eq!(dependency("sub\\app3").resolve(&input, &[]), Ok(dist.join("sub").join("app3")));
+        assert_eq!(dependency("app4").resolve(&input, &[]), Err("executable not found"));
+        assert_eq!(dependency("../outside").resolve(&input, &[]), Err("parent directory component"));
+        assert_eq!(dependency("../../etc/passwd").resolve(&input, &[]), Err("parent directory component"));
+        assert_eq!(dependency("/etc/passwd").resolve(&input, &[]), Err("absolute path"));
+        assert_eq!(dependency("C:\\Windows\\notepad").resolve(&input, &[]), Err("drive letter"));
+        // Executables given by hand are matched by name wherever they are
+        assert_eq!(dependency("../outside").resolve(&input, &[root.join("outside.exe")]), Ok(root.join("outside.exe")));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("../outside.exe", dist.join("link.exe")).unwrap();
+            assert_eq!(dependency("link").resolve(&input, &[]), Err("executable outside of the input directory"));
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,858 @@
+// Bytecode listings of code objects in the format of the `dis` module: the details `dis.code_info`
+// prints, then the instructions with their line numbers, then the nested code objects. Each version
+// is laid out like its own `dis`, except 3.13 which keeps the offsets of 3.12 instead of labels
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::marshal::{self, Code, Value};
+use crate::opcode::{Opcodes, CMP_OP, INTRINSIC_1, INTRINSIC_2, NB_OPS};
+use crate::pyc::PythonVersion;
+
+const FLAG_NAMES: [&str; 10] = [
+    "OPTIMIZED", "NEWLOCALS", "VARARGS", "VARKEYWORDS", "NESTED", "GENERATOR", "NOFREE", "COROUTINE",
+    "ITERABLE_COROUTINE", "ASYNC_GENERATOR",
+];
+
+const FUNCTION_FLAGS: [&str; 4] = ["defaults", "kwdefaults", "annotations", "closure"];
+const CONVERSIONS: [&str; 4] = ["", "str", "repr", "ascii"];
+
+// Jumps whose argument is an offset from the next instruction before 3.11, 3.11 made every jump relative
+const RELATIVE_JUMPS: [&str; 8] = [
+    "FOR_ITER", "JUMP_FORWARD", "SETUP_LOOP", "SETUP_EXCEPT", "SETUP_FINALLY", "SETUP_WITH",
+    "SETUP_ASYNC_WITH", "CALL_FINALLY",
+];
+const ABSOLUTE_JUMPS: [&str; 7] = [
+    "JUMP_IF_FALSE_OR_POP", "JUMP_IF_TRUE_OR_POP", "JUMP_ABSOLUTE", "POP_JUMP_IF_FALSE", "POP_JUMP_IF_TRUE",
+    "CONTINUE_LOOP", "JUMP_IF_NOT_EXC_MATCH",
+];
+
+const CONST_OPS: [&str; 3] = ["LOAD_CONST", "RETURN_CONST", "KW_NAMES"];
+const NAME_OPS: [&str; 15] = [
+    "STORE_NAME", "DELETE_NAME", "STORE_ATTR", "DELETE_ATTR", "STORE_GLOBAL", "DELETE_GLOBAL", "LOAD_NAME",
+    "LOAD_ATTR", "IMPORT_NAME", "IMPORT_FROM", "LOAD_GLOBAL", "LOAD_METHOD", "LOAD_SUPER_ATTR",
+    "LOAD_FROM_DICT_OR_GLOBALS", "STORE_ANNOTATION",
+];
+const LOCAL_OPS: [&str; 5] = ["LOAD_FAST", "STORE_FAST", "DELETE_FAST", "LOAD_FAST_CHECK", "LOAD_FAST_AND_CLEAR"];
+const LOCAL_PAIR_OPS: [&str; 3] = ["LOAD_FAST_LOAD_FAST", "STORE_FAST_LOAD_FAST", "STORE_FAST_STORE_FAST"];
+const FREE_OPS: [&str; 7] = [
+    "LOAD_CLOSURE", "LOAD_DEREF", "STORE_DEREF", "DELETE_DEREF", "LOAD_CLASSDEREF", "MAKE_CELL",
+    "LOAD_FROM_DICT_OR_DEREF",
+];
+
+/// Whether code objects of this version can be disassembled
+pub fn supports(version: PythonVersion) -> bool {
+    Opcodes::for_version(version).is_some()
+}
+
+/// Listing of marshalled code, a pyc without its header, compiled by the given python version
+pub fn disassemble(data: &[u8], version: PythonVersion) -> Result<String> {
+    let opcodes = Opcodes::for_version(version)
+        .ok_or_else(|| Error::Marshal(format!("no opcode table for python {}", version)))?;
+
+    match marshal::loads_code(data, version)? {
+        Value::Code(code) => Ok(Listing { code: &code, opcodes: &opcodes }.to_string()),
+        _ => Err(Error::Marshal("not a code object".to_string())),
+    }
+}
+
+struct Instruction {
+    offset: usize,
+    op: u8,
+    name: Option<&'static str>,
+    arg: Option<u32>,
+    /// Offset of the next instruction, inline caches included
+    next: usize
+}
+
+fn decode(code: &[u8], opcodes: &Opcodes) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut extended_arg = 0;
+    let mut pos = 0;
+
+    while pos < code.len() {
+        let op = code[pos];
+        let name = opcodes.name(op);
+        let offset = pos;
+
+        let arg = if opcodes.is_wordcode() {
+            pos += 2;
+            Some(code.get(offset + 1).copied().unwrap_or(0) as u32 | extended_arg)
+        } else if opcodes.has_argument(op) {
+            pos += 3;
+            let bytes = [code.get(offset + 1).copied().unwrap_or(0), code.get(offset + 2).copied().unwrap_or(0)];
+            Some(u16::from_le_bytes(bytes) as u32 | extended_arg)
+        } else {
+            pos += 1;
+            None
+        };
+
+        extended_arg = match (name, arg) {
+            (Some("EXTENDED_ARG"), Some(arg)) => arg << opcodes.extended_arg_shift(),
+            _ => 0,
+        };
+
+        // 3.11+ instructions are followed by inline cache entries, zeroed in marshalled code
+        if opcodes.version >= PythonVersion::new(3, 11) {
+            while pos < code.len() && code[pos] == 0 {
+                pos += 2;
+            }
+        }
+
+        let arg = arg.filter(|_| opcodes.has_argument(op));
+        instructions.push(Instruction { offset, op, name, arg, next: pos });
+    }
+
+    instructions
+}
+
+fn jump_target(instruction: &Instruction, name: &str, version: PythonVersion) -> Option<usize> {
+    let arg = instruction.arg? as usize;
+    let version = (version.major, version.minor);
+
+    // Jump arguments count instructions from 3.10, bytes before
+    let arg = if version >= (3, 10) { arg * 2 } else { arg };
+
+    let relative = if version >= (3, 11) {
+        matches!(name, "FOR_ITER" | "JUMP_FORWARD" | "JUMP_BACKWARD" | "JUMP_BACKWARD_NO_INTERRUPT" | "SEND"
+            | "JUMP_IF_FALSE_OR_POP" | "JUMP_IF_TRUE_OR_POP") || name.starts_with("POP_JUMP_")
+    } else {
+        RELATIVE_JUMPS.contains(&name)
+    };
+
+    if relative && name.contains("BACKWARD") {
+        instruction.next.checked_sub(arg)
+    } else if relative {
+        Some(instruction.next + arg)
+    } else if ABSOLUTE_JUMPS.contains(&name) && version < (3, 11) {
+        Some(arg)
+    } else {
+        None
+    }
+}
+
+// Line starts as (offset, line) from `co_lnotab`, line increments are signed from 3.6
+fn lnotab_lines(code: &Code, signed: bool) -> Vec<(usize, i64)> {
+    let mut starts = Vec::new();
+    let mut last_line = None;
+    let mut line = code.firstlineno as i64;
+    let mut offset = 0;
+
+    for pair in code.linetable.chunks_exact(2) {
+        if pair[0] != 0 {
+            if last_line != Some(line) {
+                starts.push((offset, line));
+                last_line = Some(line);
+            }
+            offset += pair[0] as usize;
+        }
+        line += if signed { pair[1] as i8 as i64 } else { pair[1] as i64 };
+    }
+
+    if last_line != Some(line) {
+        starts.push((offset, line));
+    }
+    starts
+}
+
+// 3.10 `co_linetable`: (offset delta, signed line delta) pairs, a line delta of -128 means no line
+fn linetable_ranges(code: &Code) -> Vec<(usize, Option<i64>)> {
+    let mut line = code.firstlineno as i64;
+    let mut offset = 0;
+
+    code.linetable.chunks_exact(2).map(|pair| {
+        let start = offset;
+        offset += pair[0] as usize;
+        let delta = pair[1] as i8;
+        if delta == -128 {
+            (start, None)
+        } else {
+            line += delta as i64;
+            (start, Some(line))
+        }
+    }).collect()
+}
+
+// Variable length integers of the 3.11+ location table, 6 bits per byte with the least significant first
+fn read_varint(table: &[u8], pos: &mut usize) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    while let Some(&b) = table.get(*pos) {
+        *pos += 1;
+        value |= ((b & 63) as u64) << shift;
+        shift += 6;
+        if b & 64 == 0 || shift >= 64 {
+            break;
+        }
+    }
+    value
+}
+
+fn read_signed_varint(table: &[u8], pos: &mut usize) -> i64 {
+    let value = read_varint(table, pos);
+    if value & 1 != 0 { -((value >> 1) as i64) } else { (value >> 1) as i64 }
+}
+
+// 3.11+ location table, see `Objects/locations.md`: each entry covers `(first & 7) + 1` code units
+// and its kind, in bits 3 to 6, gives how the line and columns are stored
+fn location_ranges(code: &Code) -> Vec<(usize, Option<i64>)> {
+    let table = &code.linetable;
+    let mut ranges = Vec::new();
+    let mut line = code.firstlineno as i64;
+    let mut offset = 0;
+    let mut pos = 0;
+
+    while pos < table.len() {
+        let first = table[pos];
+        pos += 1;
+        let kind = (first >> 3) & 15;
+        let units = (first & 7) as usize + 1;
+
+        let location = match kind {
+            15 => None,
+            14 => {
+                line += read_signed_varint(table, &mut pos);
+                for _ in 0..3 {
+                    read_varint(table, &mut pos);
+                }
+                Some(line)
+            }
+            13 => {
+                line += read_signed_varint(table, &mut pos);
+                Some(line)
+            }
+            10..=12 => {
+                line += kind as i64 - 10;
+                pos += 2;
+                Some(line)
+            }
+            _ => {
+                pos += 1;
+                Some(line)
+            }
+        };
+
+        ranges.push((offset, location));
+        offset += units * 2;
+    }
+
+    ranges
+}
+
+fn line_starts(code: &Code, version: PythonVersion) -> Vec<(usize, i64)> {
+    let ranges = match (version.major, version.minor) {
+        (2, _) => return lnotab_lines(code, false),
+        (3, 0..=5) => return lnotab_lines(code, false),
+        (3, 6..=9) => return lnotab_lines(code, true),
+        (3, 10) => linetable_ranges(code),
+        _ => location_ranges(code),
+    };
+
+    let mut starts = Vec::new();
+    let mut last_line = None;
+    for (offset, line) in ranges {
+        if let Some(line) = line && last_line != Some(line) {
+            starts.push((offset, line));
+            last_line = Some(line);
+        }
+    }
+    starts
+}
+
+// 3.11+ exception table entries as (start, end, target, depth, lasti), big endian 6 bit varints
+fn exception_table(code: &Code) -> Vec<(usize, usize, usize, u64, bool)> {
+    let table = &code.exceptiontable;
+    let mut pos = 0;
+
+    let mut read = || -> Option<u64> {
+        let mut b = *table.get(pos)?;
+        pos += 1;
+        let mut value = (b & 63) as u64;
+        while b & 64 != 0 {
+            b = *table.get(pos)?;
+            pos += 1;
+            value = (value << 6) | (b & 63) as u64;
+        }
+        Some(value)
+    };
+
+    let mut entries = Vec::new();
+    while let (Some(start), Some(length), Some(target), Some(depth_lasti)) = (read(), read(), read(), read()) {
+        let start = start as usize * 2;
+        entries.push((start, start + length as usize * 2, target as usize * 2, depth_lasti >> 1, depth_lasti & 1 != 0));
+    }
+    entries
+}
+
+// As `dis.pretty_flags`: each unknown flag in hexadecimal, `0x0` when there is none
+fn flag_names(flags: u32) -> String {
+    if flags == 0 {
+        return "0x0".to_string();
+    }
+
+    (0..32).map(|bit| 1u32 << bit)
+        .filter(|&flag| flags & flag != 0)
+        .map(|flag| match FLAG_NAMES.get(flag.trailing_zeros() as usize) {
+            Some(name) => name.to_string(),
+            None => format!("{:#x}", flag),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn function_flags(arg: u32) -> String {
+    FUNCTION_FLAGS.iter().enumerate()
+        .filter(|&(bit, _)| arg & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+struct Listing<'a> {
+    code: &'a Code,
+    opcodes: &'a Opcodes
+}
+
+impl Listing<'_> {
+    fn version(&self) -> (u8, u8) {
+        (self.opcodes.version.major, self.opcodes.version.minor)
+    }
+
+    fn local(&self, index: usize) -> Option<&str> {
+        let names = if self.version() >= (3, 11) { &self.code.localsplusnames } else { &self.code.varnames };
+        names.get(index).map(String::as_str)
+    }
+
+    // Cells and free variables: after the cells before 3.11, indexes of `localsplusnames` after
+    fn free(&self, index: usize) -> Option<&str> {
+        if self.version() >= (3, 11) {
+            return self.code.localsplusnames.get(index).map(String::as_str);
+        }
+        self.code.cellvars.iter().chain(&self.code.freevars).nth(index).map(String::as_str)
+    }
+
+    fn argrepr(&self, instruction: &Instruction, name: &str, arg: u32) -> Option<String> {
+        let version = self.version();
+        let index = arg as usize;
+
+        // 3.11 shows no constant for KW_NAMES
+        if CONST_OPS.contains(&name) && (name != "KW_NAMES" || version >= (3, 12)) {
+            let repr = if version < (3, 0) { Value::repr_python2 } else { Value::repr };
+            return self.code.consts.get(index).map(repr);
+        }
+
+        if NAME_OPS.contains(&name) {
+            // The low bits tell whether a NULL or self is pushed too, 3.13 lists it after the name
+            let (index, null) = match name {
+                "LOAD_GLOBAL" if version >= (3, 11) => (index >> 1, (arg & 1 != 0).then_some("NULL")),
+                "LOAD_ATTR" if version >= (3, 12) => (index >> 1, (arg & 1 != 0).then_some("NULL|self")),
+                "LOAD_SUPER_ATTR" => (index >> 2, (arg & 1 != 0).then_some("NULL|self")),
+                _ => (index, None),
+            };
+            let name = self.code.names.get(index)?;
+            return Some(match null {
+                Some(null) if version >= (3, 13) => format!("{} + {}", name, null),
+                Some(null) => format!("{} + {}", null, name),
+                None => name.clone(),
+            });
+        }
+
+        if LOCAL_OPS.contains(&name) {
+            return self.local(index).map(str::to_string);
+        }
+
+        if LOCAL_PAIR_OPS.contains(&name) {
+            return Some(format!("{}, {}", self.local(index >> 4)?, self.local(index & 15)?));
+        }
+
+        if FREE_OPS.contains(&name) {
+            return self.free(index).map(str::to_string);
+        }
+
+        // Absolute jumps only show their target from 3.10
+        if let Some(target) = jump_target(instruction, name, self.opcodes.version)
+            && (version >= (3, 10) || !ABSOLUTE_JUMPS.contains(&name)) {
+            return Some(format!("to {}", target));
+        }
+
+        match name {
+            "COMPARE_OP" => {
+                let shift = match version { (3, 12) => 4, (3, 13) => 5, _ => 0 };
+                let operator = CMP_OP.get(index >> shift)?;
+                Some(if version >= (3, 13) && arg & 16 != 0 { format!("bool({})", operator) } else { operator.to_string() })
+            }
+            "IS_OP" if version >= (3, 13) => Some(if arg != 0 { "is not" } else { "is" }.to_string()),
+            "CONTAINS_OP" if version >= (3, 13) => Some(if arg != 0 { "not in" } else { "in" }.to_string()),
+            "BINARY_OP" => NB_OPS.get(index).map(|op| op.to_string()),
+            "CALL_INTRINSIC_1" => INTRINSIC_1.get(index).map(|op| op.to_string()),
+            "CALL_INTRINSIC_2" => INTRINSIC_2.get(index).map(|op| op.to_string()),
+            "FORMAT_VALUE" => {
+                let conversion = CONVERSIONS[index & 3];
+                Some(match arg & 4 != 0 {
+                    true if conversion.is_empty() => "with format".to_string(),
+                    true => format!("{}, with format", conversion),
+                    false => conversion.to_string(),
+                }).filter(|text| !text.is_empty())
+            }
+            "CONVERT_VALUE" => CONVERSIONS.get(index).filter(|text| !text.is_empty()).map(|text| text.to_string()),
+            "MAKE_FUNCTION" if version >= (3, 8) => Some(function_flags(arg)).filter(|text| !text.is_empty()),
+            "SET_FUNCTION_ATTRIBUTE" => Some(function_flags(arg)).filter(|text| !text.is_empty()),
+            _ => None,
+        }
+    }
+
+    fn write_info(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let code = self.code;
+        let version = self.version();
+        let repr = if version < (3, 0) { Value::repr_python2 } else { Value::repr };
+
+        writeln!(f, "Name:              {}", code.name)?;
+        writeln!(f, "Filename:          {}", code.filename)?;
+        writeln!(f, "Argument count:    {}", code.argcount)?;
+        if version >= (3, 8) {
+            writeln!(f, "Positional-only arguments: {}", code.posonlyargcount)?;
+        }
+        if version >= (3, 0) {
+            writeln!(f, "Kw-only arguments: {}", code.kwonlyargcount)?;
+        }
+        writeln!(f, "Number of locals:  {}", code.nlocals)?;
+        writeln!(f, "Stack size:        {}", code.stacksize)?;
+        writeln!(f, "Flags:             {}", flag_names(code.flags))?;
+
+        let sections: [(&str, Vec<String>); 5] = [
+            ("Constants", code.consts.iter().map(repr).collect()),
+            ("Names", code.names.clone()),
+            ("Variable names", code.varnames.clone()),
+            ("Free variables", code.freevars.clone()),
+            ("Cell variables", code.cellvars.clone()),
+        ];
+
+        for (title, items) in sections.iter().filter(|(_, items)| !items.is_empty()) {
+            writeln!(f, "{}:", title)?;
+            for (i, item) in items.iter().enumerate() {
+                writeln!(f, "{:>4}: {}", i, item)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The columns of `dis`: line number, current instruction, jump target marker, offset, name,
+    // argument and its meaning. Python 2 pads the lines with spaces, `dis` 3 trims them
+    fn write_instructions(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let version = self.version();
+        let instructions = decode(&self.code.code, self.opcodes);
+        let lines = line_starts(self.code, self.opcodes.version);
+        let entries = exception_table(self.code);
+
+        let mut targets: Vec<usize> = instructions.iter()
+            .filter_map(|instruction| jump_target(instruction, instruction.name?, self.opcodes.version))
+            .collect();
+        targets.extend(entries.iter().map(|&(_, _, target, _, _)| target));
+
+        // From 3.7 both columns widen for the largest value, before they only pad it. 3.10 drops the line
+        // numbers of code without any
+        let line_width = match lines.iter().map(|&(_, line)| line).max() {
+            None if version >= (3, 10) => 0,
+            Some(line) if line >= 1000 && version >= (3, 7) => line.to_string().len(),
+            _ => 3,
+        };
+        let last_offset = self.code.code.len().saturating_sub(2);
+        let offset_width = if last_offset >= 10000 && version >= (3, 7) { last_offset.to_string().len() } else { 4 };
+
+        let mut next_line = lines.iter().peekable();
+
+        for instruction in &instructions {
+            // The line of the first instruction at or after its start
+            let mut line = None;
+            while let Some(&&(offset, number)) = next_line.peek() && offset <= instruction.offset {
+                line = Some(number);
+                next_line.next();
+            }
+
+            if line.is_some() && instruction.offset > 0 {
+                writeln!(f)?;
+            }
+
+            let mut fields = Vec::new();
+            if line_width > 0 {
+                fields.push(match line {
+                    Some(number) => format!("{:>width$}", number, width = line_width),
+                    None => " ".repeat(line_width),
+                });
+            }
+            fields.push("   ".to_string());
+            fields.push(if targets.contains(&instruction.offset) { ">>" } else { "  " }.to_string());
+            fields.push(format!("{:>width$}", instruction.offset, width = offset_width));
+            fields.push(match instruction.name {
+                Some(name) => format!("{:<20}", name),
+                None => format!("{:<20}", format!("<{}>", instruction.op)),
+            });
+
+            if let Some(arg) = instruction.arg {
+                fields.push(format!("{:>5}", arg));
+                let argrepr = instruction.name.and_then(|name| self.argrepr(instruction, name, arg));
+                if let Some(argrepr) = argrepr.filter(|argrepr| !argrepr.is_empty()) {
+                    fields.push(format!("({})", argrepr));
+                }
+            }
+
+            let text = fields.join(" ");
+            writeln!(f, "{}", if version >= (3, 0) { text.trim_end() } else { &text })?;
+        }
+
+        if !entries.is_empty() {
+            writeln!(f, "ExceptionTable:")?;
+        }
+        // `dis` shows the offset of the last instruction covered, the table stores the end
+        for (start, end, target, depth, lasti) in entries {
+            writeln!(f, "  {} to {} -> {} [{}]{}", start, end as i64 - 2, target, depth, if lasti { " lasti" } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Listing<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_info(f)?;
+        writeln!(f, "Disassembly:")?;
+        self.write_instructions(f)?;
+
+        for value in &self.code.consts {
+            if let Value::Code(code) = value {
+                writeln!(f)?;
+                writeln!(f, "Disassembly of {}:", value.repr())?;
+                write!(f, "{}", Listing { code, opcodes: self.opcodes })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // Listings of `dis`, addresses left out of the code reprs, for this module compiled as m.py. 3.9 and 3.12
+    // compile it without the L suffix
+    //     def f(a, b=u'caf\xe9'):
+    //         try:
+    //             return a + b + 'x'
+    //         except ValueError:
+    //             return 10L if a else 'no'
+    //     while f(1):
+    //         pass
+
+    const PYTHON27_CODE: &str = "630000000000000000020000004000000073230000006400006401008401005a0000781000650000640200830100721e0071\
+        0f00576403005328040000007505000000636166c3a9630200000002000000050000004300000073330000007910007c0000\
+        7c0100176401001753576e1c00047400006b0a00722e000101017c0000722a00640200536403005358640000532804000000\
+        4e7401000000786c010000000a0074020000006e6f2801000000740a00000056616c75654572726f72280200000074010000\
+        00617401000000622800000000280000000073040000006d2e70797401000000660100000073080000000001030110010d01\
+        69010000004e2801000000520500000028000000002800000000280000000073040000006d2e707974080000003c6d6f6475\
+        6c653e0100000073040000000c050f01";
+
+    const PYTHON27_LISTING: &str = r#"Name:              <module>
+Filename:          m.py
+Argument count:    0
+Number of locals:  0
+Stack size:        2
+Flags:             NOFREE
+Constants:
+   0: u'caf\xe9'
+   1: <code object f, file "m.py", line 1>
+   2: 1
+   3: None
+Names:
+   0: f
+Disassembly:
+  1           0 LOAD_CONST               0 (u'caf\xe9')
+              3 LOAD_CONST               1 (<code object f, file "m.py", line 1>)
+              6 MAKE_FUNCTION            1
+              9 STORE_NAME               0 (f)
+
+  6          12 SETUP_LOOP              16 (to 31)
+        >>   15 LOAD_NAME                0 (f)
+             18 LOAD_CONST               2 (1)
+             21 CALL_FUNCTION            1
+             24 POP_JUMP_IF_FALSE       30
+
+  7          27 JUMP_ABSOLUTE           15
+        >>   30 POP_BLOCK           
+        >>   31 LOAD_CONST               3 (None)
+             34 RETURN_VALUE        
+
+Disassembly of <code object f, file "m.py", line 1>:
+Name:              f
+Filename:          m.py
+Argument count:    2
+Number of locals:  2
+Stack size:        5
+Flags:             OPTIMIZED, NEWLOCALS, NOFREE
+Constants:
+   0: None
+   1: 'x'
+   2: 10L
+   3: 'no'
+Names:
+   0: ValueError
+Variable names:
+   0: a
+   1: b
+Disassembly:
+  2           0 SETUP_EXCEPT            16 (to 19)
+
+  3           3 LOAD_FAST                0 (a)
+              6 LOAD_FAST                1 (b)
+              9 BINARY_ADD          
+             10 LOAD_CONST               1 ('x')
+             13 BINARY_ADD          
+             14 RETURN_VALUE        
+             15 POP_BLOCK           
+             16 JUMP_FORWARD            28 (to 47)
+
+  4     >>   19 DUP_TOP             
+             20 LOAD_GLOBAL              0 (ValueError)
+             23 COMPARE_OP              10 (exception match)
+             26 POP_JUMP_IF_FALSE       46
+             29 POP_TOP             
+             30 POP_TOP             
+             31 POP_TOP             
+
+  5          32 LOAD_FAST                0 (a)
+             35 POP_JUMP_IF_FALSE       42
+             38 LOAD_CONST               2 (10L)
+             41 RETURN_VALUE        
+        >>   42 LOAD_CONST               3 ('no')
+             45 RETURN_VALUE        
+        >>   46 END_FINALLY         
+        >>   47 LOAD_CONST               0 (None)
+             50 RETURN_VALUE        
+"#;
+
+    const PYTHON39_CODE: &str = "63000000000000000000000000000000000300000040000000731800000064056401640284015a006500640383017214710a\
+        640453002906f505000000636166c3a96302000000000000000000000002000000080000004300000073320000007a0e7c00\
+        7c011700640117005700530004007400792c0100010001007c00722464026e02640306005900530030006400530029044eda\
+        0178e90a0000005a026e6f2901da0a56616c75654572726f722902da0161da0162a9007206000000fa046d2e7079da016601\
+        0000007308000000000102010e010c017208000000e9010000004e2901720000000029017208000000720600000072060000\
+        0072060000007207000000da083c6d6f64756c653e0100000073040000000a050801";
+
+    const PYTHON39_LISTING: &str = r#"Name:              <module>
+Filename:          m.py
+Argument count:    0
+Positional-only arguments: 0
+Kw-only arguments: 0
+Number of locals:  0
+Stack size:        3
+Flags:             NOFREE
+Constants:
+   0: 'café'
+   1: <code object f, file "m.py", line 1>
+   2: 'f'
+   3: 1
+   4: None
+   5: ('café',)
+Names:
+   0: f
+Disassembly:
+  1           0 LOAD_CONST               5 (('café',))
+              2 LOAD_CONST               1 (<code object f, file "m.py", line 1>)
+              4 LOAD_CONST               2 ('f')
+              6 MAKE_FUNCTION            1 (defaults)
+              8 STORE_NAME               0 (f)
+
+  6     >>   10 LOAD_NAME                0 (f)
+             12 LOAD_CONST               3 (1)
+             14 CALL_FUNCTION            1
+             16 POP_JUMP_IF_FALSE       20
+
+  7          18 JUMP_ABSOLUTE           10
+        >>   20 LOAD_CONST               4 (None)
+             22 RETURN_VALUE
+
+Disassembly of <code object f, file "m.py", line 1>:
+Name:              f
+Filename:          m.py
+Argument count:    2
+Positional-only arguments: 0
+Kw-only arguments: 0
+Number of locals:  2
+Stack size:        8
+Flags:             OPTIMIZED, NEWLOCALS, NOFREE
+Constants:
+   0: None
+   1: 'x'
+   2: 10
+   3: 'no'
+Names:
+   0: ValueError
+Variable names:
+   0: a
+   1: b
+Disassembly:
+  2           0 SETUP_FINALLY           14 (to 16)
+
+  3           2 LOAD_FAST                0 (a)
+              4 LOAD_FAST                1 (b)
+              6 BINARY_ADD
+              8 LOAD_CONST               1 ('x')
+             10 BINARY_ADD
+             12 POP_BLOCK
+             14 RETURN_VALUE
+
+  4     >>   16 DUP_TOP
+             18 LOAD_GLOBAL              0 (ValueError)
+             20 JUMP_IF_NOT_EXC_MATCH    44
+             22 POP_TOP
+             24 POP_TOP
+             26 POP_TOP
+
+  5          28 LOAD_FAST                0 (a)
+             30 POP_JUMP_IF_FALSE       36
+             32 LOAD_CONST               2 (10)
+             34 JUMP_FORWARD             2 (to 38)
+        >>   36 LOAD_CONST               3 ('no')
+        >>   38 ROT_FOUR
+             40 POP_EXCEPT
+             42 RETURN_VALUE
+        >>   44 RERAISE
+             46 LOAD_CONST               0 (None)
+             48 RETURN_VALUE
+"#;
+
+    const PYTHON312_CODE: &str = "630000000000000000000000000300000000000000f33200000097006404640184015a00020065006402ab01000000000000\
+        720b0900020065006402ab0100000000000072018c0a790379032905f505000000636166c3a9630200000000000000000000\
+        000400000003000000f342000000970009007c007c017a00000064017a00000053002300740000000000000000002400720b\
+        01007c00720464026302590053006403630259005300770078035900770129044eda0178e90a000000da026e6f2901da0a56\
+        616c75654572726f722902da0161da016273020000002020fa046d2e7079da0166720a0000000100000073310000008000f0\
+        02030521d80f10903189759073897bd0081af8dc0b15f200010521d915168872d208209844d20820f003010521fa73100000\
+        0082070a008a0d1e0399021e039d011e03e9010000004e290172010000002901720a000000a900f3000000007209000000fa\
+        083c6d6f64756c653e720e00000001000000731f000000f003010101f302040121f10a00070888018464d80408f103000708\
+        88018764720d000000";
+
+    const PYTHON312_LISTING: &str = r#"Name:              <module>
+Filename:          m.py
+Argument count:    0
+Positional-only arguments: 0
+Kw-only arguments: 0
+Number of locals:  0
+Stack size:        3
+Flags:             0x0
+Constants:
+   0: 'café'
+   1: <code object f, file "m.py", line 1>
+   2: 1
+   3: None
+   4: ('café',)
+Names:
+   0: f
+Disassembly:
+  0           0 RESUME                   0
+
+  1           2 LOAD_CONST               4 (('café',))
+              4 LOAD_CONST               1 (<code object f, file "m.py", line 1>)
+              6 MAKE_FUNCTION            1 (defaults)
+              8 STORE_NAME               0 (f)
+
+  6          10 PUSH_NULL
+             12 LOAD_NAME                0 (f)
+             14 LOAD_CONST               2 (1)
+             16 CALL                     1
+             24 POP_JUMP_IF_FALSE       11 (to 48)
+
+  7     >>   26 NOP
+
+  6          28 PUSH_NULL
+             30 LOAD_NAME                0 (f)
+             32 LOAD_CONST               2 (1)
+             34 CALL                     1
+             42 POP_JUMP_IF_FALSE        1 (to 46)
+             44 JUMP_BACKWARD           10 (to 26)
+        >>   46 RETURN_CONST             3 (None)
+        >>   48 RETURN_CONST             3 (None)
+
+Disassembly of <code object f, file "m.py", line 1>:
+Name:              f
+Filename:          m.py
+Argument count:    2
+Positional-only arguments: 0
+Kw-only arguments: 0
+Number of locals:  2
+Stack size:        4
+Flags:             OPTIMIZED, NEWLOCALS
+Constants:
+   0: None
+   1: 'x'
+   2: 10
+   3: 'no'
+Names:
+   0: ValueError
+Variable names:
+   0: a
+   1: b
+Disassembly:
+  1           0 RESUME                   0
+
+  2           2 NOP
+
+  3           4 LOAD_FAST                0 (a)
+              6 LOAD_FAST                1 (b)
+              8 BINARY_OP                0 (+)
+             12 LOAD_CONST               1 ('x')
+             14 BINARY_OP                0 (+)
+             18 RETURN_VALUE
+        >>   20 PUSH_EXC_INFO
+
+  4          22 LOAD_GLOBAL              0 (ValueError)
+             32 CHECK_EXC_MATCH
+             34 POP_JUMP_IF_FALSE       11 (to 58)
+             36 POP_TOP
+
+  5          38 LOAD_FAST                0 (a)
+             40 POP_JUMP_IF_FALSE        4 (to 50)
+             42 LOAD_CONST               2 (10)
+             44 SWAP                     2
+             46 POP_EXCEPT
+             48 RETURN_VALUE
+        >>   50 LOAD_CONST               3 ('no')
+             52 SWAP                     2
+             54 POP_EXCEPT
+             56 RETURN_VALUE
+
+  4     >>   58 RERAISE                  0
+        >>   60 COPY                     3
+             62 POP_EXCEPT
+             64 RERAISE                  1
+ExceptionTable:
+  4 to 16 -> 20 [0]
+  20 to 44 -> 60 [1] lasti
+  50 to 52 -> 60 [1] lasti
+  58 to 58 -> 60 [1] lasti
+"#;
+
+    #[test]
+    fn python2_listings_match_dis() {
+        // Python 2 keeps the padding of the name column, `str` constants have no prefix and `unicode` ones do
+        let listing = disassemble(&hex(PYTHON27_CODE), PythonVersion::new(2, 7)).unwrap();
+        assert_eq!(listing, PYTHON27_LISTING);
+    }
+
+    #[test]
+    fn python3_listings_match_dis() {
+        let listing = disassemble(&hex(PYTHON39_CODE), PythonVersion::new(3, 9)).unwrap();
+        assert_eq!(listing, PYTHON39_LISTING);
+
+        // Exception handlers are jump targets, the table shows the last offset each entry covers
+        let listing = disassemble(&hex(PYTHON312_CODE), PythonVersion::new(3, 12)).unwrap();
+        assert_eq!(listing, PYTHON312_LISTING);
+    }
+
+    #[test]
+    fn flags_are_named_like_dis() {
+        assert_eq!(flag_names(0), "0x0");
+        assert_eq!(flag_names(0x43), "OPTIMIZED, NEWLOCALS, NOFREE");
+        assert_eq!(flag_names(0x1000021), "OPTIMIZED, GENERATOR, 0x1000000");
+    }
+}
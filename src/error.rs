@@ -0,0 +1,93 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// No `MEI\x0C\x0B\x0A\x0B\x0E` magic in the file
+    CookieNotFound,
+    /// The cookie at `offset` is cut short by the end of the file
+    TruncatedCookie { offset: usize },
+    /// The cookie at `offset` describes a package that does not fit in the file
+    InvalidCookie { offset: usize, reason: String },
+    /// The TOC record at `offset` cannot be parsed
+    BadToc { offset: usize, reason: String },
+    /// `start..end` does not fit in the `limit` bytes available
+    OutOfRange { start: u64, end: u64, limit: u64 },
+    /// `start..end` is not inside the package the cookie describes
+    OutsidePackage { start: u64, end: u64 },
+    /// Decompressed size that differs from the one stored in the TOC
+    SizeMismatch { expected: u64, actual: u64 },
+    /// zlib stream that does not inflate
+    Decompression(String),
+    InvalidPyz(String),
+    InvalidZip(String),
+    InvalidSplash(String),
+    Marshal(String),
+    /// A name that would be written outside of the output directory
+    UnsafePath { reason: &'static str },
+    /// Glob or regex given to a filter that does not compile
+    InvalidPattern(String),
+    /// No TOC entry nor PYZ member with this name
+    EntryNotFound(String),
+    /// The sibling executable a dependency entry refers to cannot be found or parsed
+    Dependency { path: String, reason: String }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::CookieNotFound => write!(f, "Invalid pyinstaller archive: cookie not found"),
+            Error::TruncatedCookie { offset } => write!(f, "Truncated cookie at {:#X}", offset),
+            Error::InvalidCookie { offset, reason } => write!(f, "Invalid cookie at {:#X}: {}", offset, reason),
+            Error::BadToc { offset, reason } => write!(f, "Bad TOC entry at {:#X}: {}", offset, reason),
+            Error::OutOfRange { start, end, limit } => write!(f, "Range {:#X}..{:#X} is out of bounds (size {:#X})", start, end, limit),
+            Error::OutsidePackage { start, end } => write!(f, "Range {:#X}..{:#X} is outside of the package", start, end),
+            Error::SizeMismatch { expected, actual } => write!(f, "Size mismatch: expected {} bytes, got {}", expected, actual),
+            Error::Decompression(reason) => write!(f, "Decompression failed: {}", reason),
+            Error::InvalidPyz(reason) => write!(f, "Invalid pyz: {}", reason),
+            Error::InvalidZip(reason) => write!(f, "Invalid zip: {}", reason),
+            Error::InvalidSplash(reason) => write!(f, "Invalid splash resources: {}", reason),
+            Error::Marshal(reason) => write!(f, "Invalid marshal data: {}", reason),
+            Error::UnsafePath { reason } => write!(f, "Unsafe name: {}", reason),
+            Error::InvalidPattern(reason) => write!(f, "Invalid pattern {}", reason),
+            Error::EntryNotFound(name) => write!(f, "No entry named {:?}", name),
+            Error::Dependency { path, reason } => write!(f, "Cannot use dependency {:?}: {}", path, reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Bounds checked slice, used for every offset read from the archive
+pub fn slice(data: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    range(data, start as u64, len as u64)
+}
+
+/// Same as `slice` for 64-bit offsets, which may not fit in `usize` on 32-bit targets
+pub fn range(data: &[u8], start: u64, len: u64) -> Result<&[u8]> {
+    let out_of_range = Error::OutOfRange { start, end: start.saturating_add(len), limit: data.len() as u64 };
+
+    let Some(end) = start.checked_add(len).filter(|&end| end <= data.len() as u64) else {
+        return Err(out_of_range);
+    };
+
+    // Both fit in usize since they are within the slice
+    Ok(&data[start as usize..end as usize])
+}
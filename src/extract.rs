@@ -0,0 +1,697 @@
+use std::fs;
+use std::io;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use rayon::prelude::*;
+
+use crate::archive::{Archive, PyinstEntry, ARCHIVE_ITEM_BINARY, ARCHIVE_ITEM_DEPENDENCY, ARCHIVE_ITEM_PYSOURCE, ARCHIVE_ITEM_PYZ, ARCHIVE_ITEM_RUNTIME_OPTION, ARCHIVE_ITEM_SPLASH, ARCHIVE_ITEM_SYMLINK, ARCHIVE_ITEM_ZIPFILE};
+use crate::crypto::PyzCipher;
+use crate::dependency::Dependency;
+use crate::error::{Error, Result};
+use crate::filter::Filter;
+use crate::{disasm, is_zlib_header, marshal, paths, pyc, zip};
+use crate::pyc::PythonVersion;
+use crate::pyz::{Pyz, PyzMember};
+use crate::splash::Splash;
+use crate::zip::ZipEntry;
+
+/// Settings shared by every entry of an extraction
+#[derive(Clone, Default)]
+pub struct ExtractOptions {
+    /// Prepended to code entries, see `Archive::pyc_header`
+    pub pyc_header: Vec<u8>,
+    pub cipher: Option<PyzCipher>,
+    pub filter: Filter,
+    /// Expand zip entries such as `base_library.zip` into `<name>_extracted`
+    pub expand_zips: bool,
+    /// Write symlink entries as files holding their target instead of creating links
+    pub symlinks_as_files: bool,
+    /// Write a `.dis` listing next to every script and PYZ module
+    pub disassemble: bool
+}
+
+impl ExtractOptions {
+    /// Version the code is disassembled for, the one of the pyc header magic when it is supported
+    pub fn disassembly_version(&self) -> Option<PythonVersion> {
+        let magic = self.pyc_header.get(..4)?.try_into().unwrap();
+        pyc::version_for_magic(magic).filter(|&version| self.disassemble && disasm::supports(version))
+    }
+}
+
+/// Outcome of an extraction, failures do not stop the remaining entries
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    pub written: usize,
+    /// Entries and members left out by the filter
+    pub skipped: Vec<String>,
+    /// PYZ members that could not be decrypted, kept as `.encrypted`
+    pub encrypted: Vec<String>,
+    pub failures: Vec<(String, Error)>
+}
+
+impl ExtractReport {
+    pub fn merge(&mut self, other: ExtractReport) {
+        self.written += other.written;
+        self.skipped.extend(other.skipped);
+        self.encrypted.extend(other.encrypted);
+        self.failures.extend(other.failures);
+    }
+
+    pub(crate) fn record(&mut self, name: &str, result: Result<()>) {
+        match result {
+            Ok(()) => self.written += 1,
+            Err(e) => self.failures.push((name.to_string(), e)),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.encrypted.is_empty() && self.failures.is_empty()
+    }
+}
+
+// The name is checked as text by `paths`, the directories it goes through are checked on disk: a symlink
+// created by an earlier entry would take the rest of the path anywhere
+pub(crate) fn output_path(base_path: &Path, name: &str) -> Result<PathBuf> {
+    let relative = paths::sanitize(name).map_err(|reason| Error::UnsafePath { reason })?;
+
+    let through_link = relative.parent().into_iter()
+        .flat_map(Path::ancestors)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .any(|dir| base_path.join(dir).is_symlink());
+    if through_link {
+        return Err(Error::UnsafePath { reason: "path through a symlink" });
+    }
+
+    Ok(base_path.join(relative))
+}
+
+enum MemberStatus {
+    /// With the outcome of the listing when one was asked for
+    Written(Option<Result<()>>),
+    Encrypted
+}
+
+/// Writes the listing of `code`, marshalled code without pyc header, next to `path` as `.dis`
+pub fn write_disassembly(path: &Path, code: &[u8], version: PythonVersion) -> Result<()> {
+    let listing = disasm::disassemble(code, version)?;
+    fs::write(path.with_extension("dis"), listing)?;
+    Ok(())
+}
+
+fn write_member(base_path: &Path, pyz: &Pyz, member: &PyzMember, options: &ExtractOptions) -> Result<MemberStatus> {
+    let full_path = output_path(base_path, &member.path())?;
+
+    // `exists` follows links, an existing link must not be written through
+    if full_path.symlink_metadata().is_ok() {
+        return Ok(MemberStatus::Written(None));
+    }
+
+    let content = pyz.member_data(member)?;
+
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // A member that is not a zlib stream and that the key (if any) does not decrypt is kept as the raw blob,
+    // a damaged zlib stream is a failure like any other error
+    let output = match member_contents(pyz, member, &options.pyc_header, options.cipher.as_ref()) {
+        Ok(output) => output,
+        Err(Error::Decompression(_)) if !matches!(content, [cmf, flg, ..] if is_zlib_header(*cmf, *flg)) => {
+            let mut encrypted_path = full_path.into_os_string();
+            encrypted_path.push(".encrypted");
+            fs::write(encrypted_path, content)?;
+            return Ok(MemberStatus::Encrypted);
+        }
+        Err(e) => return Err(e),
+    };
+
+    fs::write(&full_path, &output)?;
+
+    let listing = options.disassembly_version()
+        .filter(|_| member.is_code())
+        .map(|version| write_disassembly(&full_path, &output[options.pyc_header.len()..], version));
+    Ok(MemberStatus::Written(listing))
+}
+
+/// Decompressed bytes of a PYZ member as they are written out, code members get the pyc header
+pub fn member_contents(pyz: &Pyz, member: &PyzMember, pyc_header: &[u8], cipher: Option<&PyzCipher>) -> Result<Vec<u8>> {
+    let output = pyz.read_member(member, cipher)?;
+
+    if member.is_code() {
+        Ok([pyc_header, &output].concat())
+    } else {
+        Ok(output)
+    }
+}
+
+/// Decompressed bytes of a TOC entry as they are written out, compressed scripts get the pyc header
+pub fn entry_contents(archive: &Archive, entry: &PyinstEntry, pyc_header: &[u8]) -> Result<Vec<u8>> {
+    let output = archive.read_entry(entry)?;
+
+    if entry.is_compressed() && entry.type_ == ARCHIVE_ITEM_PYSOURCE {
+        Ok([pyc_header, &output].concat())
+    } else {
+        Ok(output)
+    }
+}
+
+/// Contents of a TOC entry, or of a PYZ member given by its dotted name or output path,
+/// optionally prefixed with the PYZ name (`PYZ-00.pyz/app/main.pyc`)
+pub fn read_named(archive: &Archive, name: &str, options: &ExtractOptions) -> Result<Vec<u8>> {
+    if let Some(entry) = archive.find(name) {
+        return entry_contents(archive, entry, &options.pyc_header);
+    }
+
+    for entry in archive.entries().iter().filter(|entry| entry.type_ == ARCHIVE_ITEM_PYZ) {
+        let Ok(pyz) = archive.pyz(entry) else { continue };
+        let member_name = name.strip_prefix(&entry.name)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .unwrap_or(name);
+
+        let found = pyz.find(member_name)
+            .or_else(|| pyz.members().iter().find(|member| member.path() == member_name));
+
+        if let Some(member) = found {
+            return member_contents(&pyz, member, &options.pyc_header, options.cipher.as_ref());
+        }
+    }
+
+    Err(Error::EntryNotFound(name.to_string()))
+}
+
+/// Extracts the selected members of the pyz into `out_dir`, member names in the report are prefixed with `name`
+pub fn extract_pyz(out_dir: &Path, name: &str, pyz: &Pyz, options: &ExtractOptions) -> ExtractReport {
+    let statuses: Vec<(&PyzMember, Option<Result<MemberStatus>>)> = pyz.members().par_iter()
+        .map(|member| {
+            let status = options.filter.matches_member(member)
+                .then(|| write_member(out_dir, pyz, member, options));
+            (member, status)
+        })
+        .collect();
+
+    let mut report = ExtractReport::default();
+
+    for (member, status) in statuses {
+        let member_name = format!("{}/{}", name, member.name);
+        let Some(status) = status else {
+            report.skipped.push(member_name);
+            continue;
+        };
+        match status {
+            Ok(MemberStatus::Written(listing)) => {
+                report.written += 1;
+                if let Some(result) = listing {
+                    report.record(&format!("{}.dis", member_name), result);
+                }
+            }
+            Ok(MemberStatus::Encrypted) => report.encrypted.push(member_name),
+            Err(e) => report.failures.push((member_name, e)),
+        }
+    }
+
+    report
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+// Creating links needs privileges on Windows, the target is written instead
+#[cfg(not(unix))]
+fn create_symlink(target: &str, path: &Path) -> io::Result<()> {
+    fs::write(path, target)
+}
+
+/// Creates a symlink entry, the target is the payload and must stay inside `base_path`
+pub fn write_symlink(base_path: &Path, archive: &Archive, entry: &PyinstEntry, as_file: bool) -> Result<()> {
+    let full_path = output_path(base_path, &entry.name)?;
+
+    // `exists` follows links, a dangling link would be created again
+    if full_path.symlink_metadata().is_ok() {
+        return Ok(());
+    }
+
+    let target = String::from_utf8_lossy(&archive.read_entry(entry)?).into_owned();
+    paths::resolve_link(&entry.name, &target).map_err(|reason| Error::UnsafePath { reason })?;
+
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent)?;
+
+        // The `..` of the target climb from here, it has to be a real directory of the output
+        if !fs::canonicalize(parent)?.starts_with(fs::canonicalize(base_path)?) {
+            return Err(Error::UnsafePath { reason: "link outside of the output directory" });
+        }
+    }
+
+    if as_file {
+        fs::write(full_path, target)?;
+    } else {
+        create_symlink(&target, &full_path)?;
+    }
+    Ok(())
+}
+
+// Zip entries are `Z` entries (eggs) and zip files such as `base_library.zip`
+fn is_zip_entry(entry: &PyinstEntry) -> bool {
+    entry.type_ == ARCHIVE_ITEM_ZIPFILE || entry.name.ends_with(".zip") || entry.name.ends_with(".egg")
+}
+
+fn write_zip_member(out_dir: &Path, data: &[u8], zip_entry: &ZipEntry, pyc_header: &[u8]) -> Result<()> {
+    let full_path = output_path(out_dir, &zip_entry.name)?;
+
+    if zip_entry.name.ends_with('/') {
+        fs::create_dir_all(full_path)?;
+        return Ok(());
+    }
+
+    if full_path.is_symlink() {
+        return Err(Error::UnsafePath { reason: "path through a symlink" });
+    }
+
+    let contents = zip::read(data, zip_entry)?;
+
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Zipped pyc files normally keep their header, the ones without a known magic get the archive one
+    let has_header = contents.get(..4).is_some_and(|magic| pyc::version_for_magic(magic.try_into().unwrap()).is_some());
+    if zip_entry.name.ends_with(".pyc") && !has_header {
+        fs::write(full_path, [pyc_header, &contents].concat())?;
+    } else {
+        fs::write(full_path, contents)?;
+    }
+    Ok(())
+}
+
+/// Extracts the selected members of a zip into `out_dir`, member names in the report are prefixed with `name`
+pub fn extract_zip(out_dir: &Path, name: &str, data: &[u8], options: &ExtractOptions) -> ExtractReport {
+    let mut report = ExtractReport::default();
+
+    let zip_entries = match zip::entries(data) {
+        Ok(zip_entries) => zip_entries,
+        Err(e) => {
+            report.failures.push((name.to_string(), e));
+            return report;
+        }
+    };
+
+    for zip_entry in zip_entries {
+        let member_name = format!("{}/{}", name, zip_entry.name);
+        if !options.filter.matches_zip_member(&zip_entry.name) {
+            report.skipped.push(member_name);
+            continue;
+        }
+        report.record(&member_name, write_zip_member(out_dir, data, &zip_entry, &options.pyc_header));
+    }
+
+    report
+}
+
+/// Writes the image, the Tcl script and the list of required Tcl/Tk files of a splash entry into
+/// `out_dir` as `splash.<ext>`, `splash.tcl` and `requirements.txt`
+pub fn extract_splash(out_dir: &Path, name: &str, data: &[u8]) -> ExtractReport {
+    let mut report = ExtractReport::default();
+
+    let splash = match Splash::parse(data) {
+        Ok(splash) => splash,
+        Err(e) => {
+            report.failures.push((name.to_string(), e));
+            return report;
+        }
+    };
+
+    let requirements: String = splash.requirements.iter().map(|requirement| format!("{}\n", requirement)).collect();
+    let files = [
+        (format!("splash.{}", splash.image_extension()), splash.image.as_slice()),
+        ("splash.tcl".to_string(), splash.script.as_slice()),
+        ("requirements.txt".to_string(), requirements.as_bytes()),
+    ];
+
+    if let Err(e) = fs::create_dir_all(out_dir) {
+        report.failures.push((name.to_string(), e.into()));
+        return report;
+    }
+
+    for (file_name, contents) in files {
+        let result = fs::write(out_dir.join(&file_name), contents).map_err(Into::into);
+        report.record(&format!("{}/{}", name, file_name), result);
+    }
+
+    report
+}
+
+/// Writes an entry under `base_path`
+pub fn write_nested_file(base_path: &Path, archive: &Archive, entry: &PyinstEntry, pyc_header: &[u8]) -> Result<()> {
+
+    let full_path = output_path(base_path, &entry.name)?;
+
+    // `exists` follows links, an existing link must not be written through
+    if full_path.symlink_metadata().is_ok() {
+        return Ok(());
+    }
+
+    // Read before creating the file so a bad entry does not leave an empty file behind
+    let output = entry_contents(archive, entry, pyc_header)?;
+
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(&full_path, output)?;
+    Ok(())
+}
+
+/// Writes an entry and expands PYZ entries, splash entries, and zip entries when asked, into `<name>_extracted`.
+/// The members are filtered on their own so they are extracted even when the PYZ or zip itself is not selected
+pub fn extract_entry(base_path: &Path, archive: &Archive, entry: &PyinstEntry, options: &ExtractOptions) -> ExtractReport {
+    let mut report = ExtractReport::default();
+
+    // Runtime options only have a name, see `Archive::runtime_options`, and dependencies
+    // are stored in another executable, see `extract_dependencies`
+    if entry.type_ == ARCHIVE_ITEM_RUNTIME_OPTION || entry.type_ == ARCHIVE_ITEM_DEPENDENCY {
+        return report;
+    }
+
+    if !options.filter.matches_entry(entry) {
+        report.skipped.push(entry.name.clone());
+    } else if entry.type_ == ARCHIVE_ITEM_SYMLINK {
+        report.record(&entry.name, write_symlink(base_path, archive, entry, options.symlinks_as_files));
+    } else {
+        report.record(&entry.name, write_nested_file(base_path, archive, entry, &options.pyc_header));
+
+        // Scripts stored as source by old versions are not disassembled
+        if let Some(version) = options.disassembly_version() && entry.type_ == ARCHIVE_ITEM_PYSOURCE
+            && let Ok(full_path) = output_path(base_path, &entry.name)
+            && let Ok(code) = archive.read_entry(entry) && marshal::is_code(&code) {
+            report.record(&format!("{}.dis", entry.name), write_disassembly(&full_path, &code, version));
+        }
+    }
+    if options.expand_zips && is_zip_entry(entry) && let Ok(full_path) = output_path(base_path, &entry.name) {
+        match archive.read_entry(entry) {
+            Ok(data) => {
+                let mut out_dir = full_path.into_os_string();
+                out_dir.push("_extracted");
+                report.merge(extract_zip(Path::new(&out_dir), &entry.name, &data, options));
+            }
+            Err(e) => report.failures.push((entry.name.clone(), e)),
+        }
+    }
+
+    if entry.type_ == ARCHIVE_ITEM_SPLASH && options.filter.matches_entry(entry) && let Ok(full_path) = output_path(base_path, &entry.name) {
+        match archive.read_entry(entry) {
+            Ok(data) => {
+                let mut out_dir = full_path.into_os_string();
+                out_dir.push("_extracted");
+                report.merge(extract_splash(Path::new(&out_dir), &entry.name, &data));
+            }
+            Err(e) => report.failures.push((entry.name.clone(), e)),
+        }
+    }
+
+    if entry.type_ == ARCHIVE_ITEM_PYZ && let Ok(full_path) = output_path(base_path, &entry.name) {
+        match archive.pyz(entry) {
+            Ok(pyz) => {
+                let mut out_dir = full_path.into_os_string();
+                out_dir.push("_extracted");
+                report.merge(extract_pyz(Path::new(&out_dir), &entry.name, &pyz, options));
+            }
+            Err(e) => report.failures.push((entry.name.clone(), e)),
+        }
+    }
+
+    report
+}
+
+/// Extracts the entries of the archive selected by the filter in parallel, symlinks are created
+/// last so no entry is written through one
+pub fn extract_all(base_path: &Path, archive: &Archive, options: &ExtractOptions) -> ExtractReport {
+    let (symlinks, entries): (Vec<&PyinstEntry>, Vec<&PyinstEntry>) = archive.entries().iter()
+        .partition(|entry| entry.type_ == ARCHIVE_ITEM_SYMLINK);
+
+    let mut report = entries.par_iter()
+        .map(|entry| extract_entry(base_path, archive, entry, options))
+        .reduce(ExtractReport::default, |mut a, b| {
+            a.merge(b);
+            a
+        });
+
+    for entry in symlinks {
+        report.merge(extract_entry(base_path, archive, entry, options));
+    }
+
+    report
+}
+
+// Binary entries are searched for archives this many levels deep
+const MAX_NESTING: usize = 4;
+
+/// An archive found by `extract_embedded`
+#[derive(Debug)]
+pub struct EmbeddedArchive {
+    /// Offset of the cookie in the input, then the binary entries it is nested in (`0x1F00 > payload.exe @ 0x2A0`)
+    pub location: String,
+    pub cookie_offset: usize,
+    pub entries: usize,
+    pub output: PathBuf,
+    pub report: ExtractReport
+}
+
+/// Extracts every archive in `data` into `archive_<n>` under `base_path`, then the archives
+/// inside their binary entries next to the entry as `<name>_extracted/archive_<n>`.
+/// An empty `pyc_header` and a missing cipher are taken from each archive
+pub fn extract_embedded(base_path: &Path, data: &[u8], options: &ExtractOptions) -> Vec<EmbeddedArchive> {
+    extract_nested(base_path, data, "", options, 0)
+}
+
+fn extract_nested(base_path: &Path, data: &[u8], parent: &str, options: &ExtractOptions, depth: usize) -> Vec<EmbeddedArchive> {
+    let mut found = Vec::new();
+
+    for (i, archive) in Archive::find_all(data).iter().enumerate() {
+        let location = format!("{}{:#X}", parent, archive.cookie_offset());
+        let output = base_path.join(format!("archive_{}", i));
+
+        let mut archive_options = options.clone();
+        if archive_options.pyc_header.is_empty() {
+            archive_options.pyc_header = archive.pyc_header();
+        }
+        if archive_options.cipher.is_none() {
+            archive_options.cipher = archive.crypto_key().as_deref().and_then(PyzCipher::new);
+        }
+        let report = extract_all(&output, archive, &archive_options);
+
+        found.push(EmbeddedArchive {
+            location: location.clone(),
+            cookie_offset: archive.cookie_offset(),
+            entries: archive.entries().len(),
+            output: output.clone(),
+            report
+        });
+
+        if depth + 1 >= MAX_NESTING {
+            continue;
+        }
+
+        let nested: Vec<EmbeddedArchive> = archive.entries().par_iter()
+            .filter(|entry| entry.type_ == ARCHIVE_ITEM_BINARY)
+            .flat_map_iter(|entry| {
+                let Ok(contents) = archive.read_entry(entry) else { return Vec::new() };
+                let Ok(entry_path) = output_path(&output, &entry.name) else { return Vec::new() };
+
+                let mut nested_base = entry_path.into_os_string();
+                nested_base.push("_extracted");
+                let parent = format!("{} > {} @ ", location, entry.name);
+
+                extract_nested(Path::new(&nested_base), &contents, &parent, options, depth + 1)
+            })
+            .collect();
+
+        found.extend(nested);
+    }
+
+    found
+}
+
+// Dependency output is kept apart from the entries of the archive itself
+const DEPENDENCIES_DIR: &str = "_dependencies";
+
+fn link_dependency(base_path: &Path, extracted: &Path, dependency: &Dependency, as_file: bool) -> Result<()> {
+    let relative = paths::sanitize(&dependency.filename).map_err(|reason| Error::UnsafePath { reason })?;
+    let source = output_path(extracted, &dependency.filename)?;
+
+    if !source.is_file() {
+        return Err(Error::EntryNotFound(dependency.filename.clone()));
+    }
+
+    let full_path = output_path(base_path, &dependency.filename)?;
+    if full_path.symlink_metadata().is_ok() {
+        return Ok(());
+    }
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if as_file {
+        fs::copy(&source, &full_path)?;
+        return Ok(());
+    }
+
+    // Relative link so the output tree can be moved
+    let mut target = PathBuf::new();
+    for _ in 1..relative.components().count() {
+        target.push("..");
+    }
+    target.push(source.strip_prefix(base_path).unwrap_or(&source));
+
+    create_symlink(&target.to_string_lossy(), &full_path)?;
+    Ok(())
+}
+
+/// Extracts the sibling executables that dependency entries refer to into `_dependencies/<name>`, then
+/// links each dependency to the extracted file (copies it with `symlinks_as_files`). The executables
+/// are taken from `candidates` when given there, else from the directory of `input`
+pub fn extract_dependencies(base_path: &Path, input: &Path, archive: &Archive, options: &ExtractOptions, candidates: &[PathBuf]) -> ExtractReport {
+    let mut report = ExtractReport::default();
+    let mut siblings: BTreeMap<String, Vec<(&PyinstEntry, Dependency)>> = BTreeMap::new();
+
+    for entry in archive.entries().iter().filter(|entry| entry.type_ == ARCHIVE_ITEM_DEPENDENCY) {
+        if !options.filter.matches_entry(entry) {
+            report.skipped.push(entry.name.clone());
+            continue;
+        }
+        match Dependency::parse(&entry.name) {
+            Some(dependency) => siblings.entry(dependency.path.clone()).or_default().push((entry, dependency)),
+            None => report.failures.push((entry.name.clone(), Error::BadToc {
+                offset: entry.offset as usize,
+                reason: "dependency without a path".to_string()
+            })),
+        }
+    }
+
+    for (path, dependencies) in siblings {
+        let sibling = match dependencies[0].1.resolve(input, candidates) {
+            Ok(sibling_path) => Archive::open(sibling_path).map_err(|e| e.to_string()),
+            Err(reason) => Err(reason.to_string()),
+        };
+
+        let sibling = match sibling {
+            Ok(sibling) => sibling,
+            Err(reason) => {
+                report.failures.extend(dependencies.into_iter().map(|(entry, _)| {
+                    (entry.name.clone(), Error::Dependency { path: path.clone(), reason: reason.clone() })
+                }));
+                continue;
+            }
+        };
+
+        let name = format!("{}/{}", DEPENDENCIES_DIR, dependencies[0].1.executable_name());
+        let extracted = match output_path(base_path, &name) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                report.failures.push((name, e));
+                continue;
+            }
+        };
+
+        // The whole sibling is extracted, the filter only selects the dependency entries
+        let sibling_options = ExtractOptions {
+            pyc_header: sibling.pyc_header(),
+            cipher: options.cipher.clone().or_else(|| sibling.crypto_key().as_deref().and_then(PyzCipher::new)),
+            filter: Filter::default(),
+            ..options.clone()
+        };
+        let mut sibling_report = extract_all(&extracted, &sibling, &sibling_options);
+        let prefix = |entry_name: &mut String| *entry_name = format!("{}/{}", name, entry_name);
+        sibling_report.skipped.iter_mut().for_each(prefix);
+        sibling_report.encrypted.iter_mut().for_each(prefix);
+        sibling_report.failures.iter_mut().for_each(|(entry_name, _)| prefix(entry_name));
+        report.merge(sibling_report);
+
+        for (entry, dependency) in dependencies {
+            report.record(&entry.name, link_dependency(base_path, &extracted, &dependency, options.symlinks_as_files));
+        }
+    }
+
+    report
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_path_refuses_paths_through_symlinks() {
+        let base_path = std::env::temp_dir().join(format!("extractor-output-path-{}", std::process::id()));
+        fs::create_dir_all(base_path.join("a")).unwrap();
+        create_symlink("..", &base_path.join("a/up")).unwrap();
+
+        assert_eq!(output_path(&base_path, "a/file").unwrap(), base_path.join("a/file"));
+        assert!(matches!(output_path(&base_path, "a/up/esc"), Err(Error::UnsafePath { .. })));
+        assert!(matches!(output_path(&base_path, "a/up/esc/PWNED"), Err(Error::UnsafePath { .. })));
+        // The link itself is not written through, writers check it on their own
+        assert!(output_path(&base_path, "a/up").is_ok());
+
+        fs::remove_dir_all(&base_path).unwrap();
+    }
+
+    // PYZ holding data members `(name, stored bytes)`, one past the end of the archive when the bytes are `None`
+    fn pyz_bytes(members: &[(&str, Option<&[u8]>)]) -> Vec<u8> {
+        let mut data = b"PYZ\0\0\0\0\0\0\0\0\0".to_vec();
+        let mut toc = vec![b'{'];
+
+        for (name, content) in members {
+            let (offset, size) = match content {
+                Some(content) => (data.len(), content.len()),
+                None => (1 << 20, 16),
+            };
+            data.extend_from_slice(content.unwrap_or_default());
+
+            toc.extend_from_slice(&[b'z', name.len() as u8]);
+            toc.extend_from_slice(name.as_bytes());
+            toc.extend_from_slice(&[b')', 3]);
+            for value in [2, offset as i32, size as i32] {
+                toc.push(b'i');
+                toc.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        toc.push(b'0');
+
+        let toc_offset = data.len() as u32;
+        data[8..12].copy_from_slice(&toc_offset.to_be_bytes());
+        data.extend_from_slice(&toc);
+        data
+    }
+
+    #[test]
+    fn only_undecryptable_members_are_kept_encrypted() {
+        let mut compressor = libdeflater::Compressor::new(libdeflater::CompressionLvl::default());
+        let mut plain = vec![0u8; compressor.zlib_compress_bound(2)];
+        let size = compressor.zlib_compress(b"ok", &mut plain).unwrap();
+        plain.truncate(size);
+
+        let data = pyz_bytes(&[
+            ("plain", Some(&plain)),
+            ("damaged", Some(b"\x78\x9c not deflate at all")),
+            ("secret", Some(b"\x13\x37 sixteen byte iv and ciphertext")),
+            ("missing", None),
+        ]);
+        let pyz = Pyz::parse(&data).unwrap();
+
+        for cipher in [None, PyzCipher::new("wrong key")] {
+            let base_path = std::env::temp_dir().join(format!("extractor-encrypted-{}", std::process::id()));
+            let options = ExtractOptions { cipher, ..ExtractOptions::default() };
+            let report = extract_pyz(&base_path, "PYZ", &pyz, &options);
+
+            assert_eq!(report.written, 1);
+            assert_eq!(fs::read(base_path.join("plain")).unwrap(), b"ok");
+            assert_eq!(report.encrypted, ["PYZ/secret"]);
+            assert!(base_path.join("secret.encrypted").exists());
+            let failures: Vec<&str> = report.failures.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(failures, ["PYZ/damaged", "PYZ/missing"]);
+            assert!(!base_path.join("damaged.encrypted").exists());
+
+            fs::remove_dir_all(&base_path).unwrap();
+        }
+    }
+}
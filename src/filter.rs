@@ -0,0 +1,218 @@
+// Entry selection by glob, regex and type code
+//
+// Names come from untrusted samples, both kinds of patterns are compiled by the `regex` engine
+// which matches in linear time whatever the pattern.
+
+use globset::GlobBuilder;
+
+use crate::archive::{PyinstEntry, ARCHIVE_ITEM_DATA, ARCHIVE_ITEM_PYMODULE, ARCHIVE_ITEM_PYPACKAGE};
+use crate::error::{Error, Result};
+use crate::pyz::PyzMember;
+
+fn invalid(pattern: &str, error: impl std::fmt::Display) -> Error {
+    Error::InvalidPattern(format!("{:?}: {}", pattern, error))
+}
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    regex: regex::Regex
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let regex = regex::Regex::new(pattern).map_err(|e| invalid(pattern, e))?;
+        Ok(Regex { regex })
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+/// Shell style pattern: `*` and `?` stay within a path component, `**` crosses them.
+/// A pattern without `/` is matched against the file name only.
+#[derive(Debug, Clone)]
+pub struct Glob {
+    matcher: globset::GlobMatcher,
+    file_name_only: bool
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self> {
+        // Names are matched with `/` separators
+        let normalized = pattern.replace('\\', "/");
+
+        let glob = GlobBuilder::new(&normalized)
+            .literal_separator(true)
+            .backslash_escape(false)
+            .build()
+            .map_err(|e| invalid(pattern, e.kind()))?;
+
+        Ok(Glob {
+            matcher: glob.compile_matcher(),
+            file_name_only: !normalized.contains('/'),
+        })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        let path = path.replace('\\', "/");
+        if self.file_name_only {
+            self.matcher.is_match(path.rsplit('/').next().unwrap_or(&path))
+        } else {
+            self.matcher.is_match(&path)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Glob(Glob),
+    Regex(Regex)
+}
+
+impl Pattern {
+    fn is_match(&self, name: &str) -> bool {
+        match self {
+            Pattern::Glob(glob) => glob.is_match(name),
+            Pattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Selection of CArchive entries and PYZ members, an empty filter selects everything
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    /// Allowed type codes, PYZ members count as `m` (module), `M` (package) or `x` (data)
+    pub types: Option<Vec<u8>>
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && self.types.is_none()
+    }
+
+    fn matches(&self, names: &[&str], type_: u8) -> bool {
+        if let Some(types) = &self.types && !types.contains(&type_) {
+            return false;
+        }
+
+        let any = |patterns: &[Pattern]| patterns.iter().any(|pattern| names.iter().any(|name| pattern.is_match(name)));
+
+        (self.include.is_empty() || any(&self.include)) && !any(&self.exclude)
+    }
+
+    pub fn matches_entry(&self, entry: &PyinstEntry) -> bool {
+        self.matches(&[&entry.name], entry.type_)
+    }
+
+    /// Zip members count as modules when they are pyc files and as data otherwise
+    pub fn matches_zip_member(&self, name: &str) -> bool {
+        let type_ = if name.ends_with(".pyc") { ARCHIVE_ITEM_PYMODULE } else { ARCHIVE_ITEM_DATA };
+        self.matches(&[name], type_)
+    }
+
+    /// Streams carved by a salvage have no TOC record, they count as data
+    pub fn matches_carved(&self, name: &str) -> bool {
+        self.matches(&[name], ARCHIVE_ITEM_DATA)
+    }
+
+    /// Members are matched on their output path (`pkg/mod.pyc`) and their dotted module name
+    pub fn matches_member(&self, member: &PyzMember) -> bool {
+        let type_ = if !member.is_code() {
+            ARCHIVE_ITEM_DATA
+        } else if member.is_package() {
+            ARCHIVE_ITEM_PYPACKAGE
+        } else {
+            ARCHIVE_ITEM_PYMODULE
+        };
+
+        self.matches(&[&member.path(), &member.name], type_)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex(pattern: &str) -> Regex {
+        Regex::new(pattern).unwrap()
+    }
+
+    fn glob(pattern: &str) -> Glob {
+        Glob::new(pattern).unwrap()
+    }
+
+    #[test]
+    fn regex_anchors() {
+        assert!(regex("^main").is_match("main.pyc"));
+        assert!(!regex("^main").is_match("app/main.pyc"));
+        assert!(regex(r"\.pyc$").is_match("app/main.pyc"));
+        assert!(!regex(r"\.pyc$").is_match("app/main.pyc.bak"));
+        assert!(regex("^$").is_match(""));
+    }
+
+    #[test]
+    fn regex_classes() {
+        assert!(regex(r"^lib[a-z]+\d\.so$").is_match("libz1.so"));
+        assert!(!regex(r"^lib[a-z]+\d\.so$").is_match("libZ1.so"));
+        assert!(regex(r"[^/]+\.pyd$").is_match("a/b.pyd"));
+        assert!(regex(r"^\w+\s\S$").is_match("a_1 x"));
+    }
+
+    #[test]
+    fn regex_repetition() {
+        assert!(regex("^a{2,3}$").is_match("aaa"));
+        assert!(!regex("^a{2,3}$").is_match("aaaa"));
+        assert!(regex("^x{0,3}$").is_match("xx"));
+        assert!(!regex("^x{0,3}$").is_match("xxxx"));
+        assert!(regex("^(ab|cd)+?e*$").is_match("abcdab"));
+        assert!(regex("^(?:py)?mod$").is_match("mod"));
+    }
+
+    #[test]
+    fn regex_errors() {
+        assert!(matches!(Regex::new("(a"), Err(Error::InvalidPattern(_))));
+        assert!(matches!(Regex::new("*a"), Err(Error::InvalidPattern(_))));
+        assert!(matches!(Regex::new("[z-a]"), Err(Error::InvalidPattern(_))));
+        // Python's `{,n}` needs its lower bound
+        assert!(matches!(Regex::new("x{,3}"), Err(Error::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn glob_components() {
+        assert!(glob("*.pyd").is_match("lib/sub/_ssl.pyd"));
+        assert!(glob("lib/*.pyd").is_match("lib/_ssl.pyd"));
+        assert!(!glob("lib/*.pyd").is_match("lib/sub/_ssl.pyd"));
+        assert!(glob("lib/**/*.pyd").is_match("lib/_ssl.pyd"));
+        assert!(glob("lib/**/*.pyd").is_match("lib/a/b/_ssl.pyd"));
+        assert!(glob("**/test/**").is_match("pkg/test/x.pyc"));
+        assert!(glob("lib\\*.pyd").is_match("lib\\_ssl.pyd"));
+        assert!(glob("main.py?").is_match("main.pyc"));
+        assert!(!glob("a?b").is_match("a/b"));
+    }
+
+    #[test]
+    fn glob_classes() {
+        assert!(glob("[a-c]*.so").is_match("b.so"));
+        assert!(!glob("[!a-c]*.so").is_match("b.so"));
+        assert!(glob("[!a-c]*.so").is_match("d.so"));
+        assert!(matches!(Glob::new("[abc"), Err(Error::InvalidPattern(_))));
+    }
+
+    // Patterns that take exponential time in a backtracking matcher, the automata of `regex` run them
+    // in one pass. What they cannot bound is their own size: patterns that compile too large are refused
+    #[test]
+    fn pathological_patterns() {
+        assert!(!regex("(a*)*b").is_match(&"a".repeat(28)));
+        assert!(!regex("^(a|a)*$").is_match(&format!("{}b", "a".repeat(40))));
+        assert!(!regex("^(a+)+$").is_match(&format!("{}!", "a".repeat(40))));
+        assert!(!glob(&"*a".repeat(30)).is_match(&"a".repeat(29)));
+        assert!(!glob(&format!("{}b", "**/".repeat(20))).is_match(&"a/".repeat(40)));
+
+        assert!(matches!(Regex::new("(a{1000}){1000}"), Err(Error::InvalidPattern(_))));
+        assert!(matches!(Regex::new(r"\w{1000}"), Err(Error::InvalidPattern(_))));
+    }
+}
@@ -0,0 +1,162 @@
+//! Parsing and extraction of PyInstaller executables.
+//!
+//! ```no_run
+//! let archive = extractor::Archive::open("sample.exe")?;
+//! for entry in archive.entries() {
+//!     let content = archive.read_entry(entry)?;
+//!     println!("{} ({} bytes)", entry.name, content.len());
+//! }
+//! # Ok::<(), extractor::Error>(())
+//! ```
+
+use std::cell::RefCell;
+use libdeflater::{DecompressionError, Decompressor};
+
+pub mod archive;
+pub mod crypto;
+pub mod dependency;
+pub mod disasm;
+pub mod error;
+pub mod extract;
+pub mod filter;
+pub mod json;
+pub mod marshal;
+pub mod opcode;
+pub mod paths;
+pub mod pyc;
+pub mod pyz;
+pub mod report;
+pub mod runtime;
+pub mod salvage;
+pub mod splash;
+pub mod verify;
+pub mod zip;
+
+pub use archive::{Archive, CookieLayout, PyinstEntry, PyinstHeader};
+pub use crypto::PyzCipher;
+pub use error::{Error, Result};
+pub use marshal::{Code, Value};
+pub use pyc::PythonVersion;
+pub use pyz::{Pyz, PyzHeader, PyzMember};
+
+thread_local! {
+    static DECOMPRESSOR: RefCell<Decompressor> = RefCell::new(Decompressor::new());
+}
+
+/// Inflates a zlib stream whose decompressed size is known, as stored in the TOC. The buffer is bounded
+/// by what `data` can expand to: a stream shorter than `size` is returned as is, a longer one is a `SizeMismatch`
+pub fn zlib_decompress(data: &[u8], size: usize) -> Result<Vec<u8>> {
+    let mut output = vec![0u8; size.min(max_inflated_size(data))];
+
+    let result = DECOMPRESSOR.with(|decompressor| decompressor.borrow_mut().zlib_decompress(data, &mut output));
+    match result {
+        Ok(actual) => {
+            output.truncate(actual);
+            Ok(output)
+        }
+        Err(DecompressionError::InsufficientSpace) => {
+            let actual = zlib_inflate(data)?.len();
+            Err(Error::SizeMismatch { expected: size as u64, actual: actual as u64 })
+        }
+        Err(e) => Err(Error::Decompression(e.to_string())),
+    }
+}
+
+// Deflate cannot expand its input more than this, bounds sizes read from untrusted headers
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+fn max_inflated_size(data: &[u8]) -> usize {
+    data.len().saturating_mul(MAX_DEFLATE_RATIO).saturating_add(1024)
+}
+
+/// Inflates a raw deflate stream such as a zip member into at most `size` bytes. The buffer is
+/// bounded by what `data` can expand to, whatever `size` claims
+pub fn deflate_decompress(data: &[u8], size: usize) -> Result<Vec<u8>> {
+    let capacity = size.min(max_inflated_size(data));
+    let mut output = vec![0u8; capacity];
+
+    let size = DECOMPRESSOR.with(|decompressor| {
+        let mut decompressor = decompressor.borrow_mut();
+        decompressor.deflate_decompress(data, &mut output)
+            .map_err(|e| Error::Decompression(e.to_string()))
+    })?;
+
+    output.truncate(size);
+    Ok(output)
+}
+
+pub(crate) fn is_zlib_header(cmf: u8, flg: u8) -> bool {
+    // Deflate with a 32K window, no preset dictionary, valid header checksum
+    cmf == 0x78 && flg & 0x20 == 0 && (cmf as u16 * 256 + flg as u16).is_multiple_of(31)
+}
+
+/// Inflates a zlib stream whose decompressed size is not known up front
+pub fn zlib_inflate(data: &[u8]) -> Result<Vec<u8>> {
+    inflate(data, (data.len() * 4).max(1024))
+}
+
+/// Inflates the zlib stream at the start of `data`, anything after the stream is ignored
+pub fn zlib_inflate_prefix(data: &[u8]) -> Result<Vec<u8>> {
+    inflate(data, 64 * 1024)
+}
+
+// The buffer grows until the stream fits, up to what `data` can expand to
+fn inflate(data: &[u8], capacity: usize) -> Result<Vec<u8>> {
+    let limit = max_inflated_size(data);
+    let mut capacity = capacity.min(limit);
+
+    DECOMPRESSOR.with(|decompressor| {
+        let mut decompressor = decompressor.borrow_mut();
+        loop {
+            let mut output = vec![0u8; capacity];
+            match decompressor.zlib_decompress(data, &mut output) {
+                Ok(size) => {
+                    output.truncate(size);
+                    return Ok(output);
+                }
+                Err(DecompressionError::InsufficientSpace) if capacity < limit => capacity = capacity.saturating_mul(4).min(limit),
+                Err(DecompressionError::InsufficientSpace) => {
+                    return Err(Error::Decompression("output larger than the stream can expand to".to_string()));
+                }
+                Err(e) => return Err(Error::Decompression(e.to_string())),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut compressor = libdeflater::Compressor::new(libdeflater::CompressionLvl::best());
+        let mut output = vec![0; compressor.zlib_compress_bound(data.len())];
+        let size = compressor.zlib_compress(data, &mut output).unwrap();
+        output.truncate(size);
+        output
+    }
+
+    #[test]
+    fn inflate_grows_up_to_the_deflate_ratio() {
+        // As compressible as deflate allows, the buffer has to grow to its limit
+        let zeros = vec![0; 16 << 20];
+        let stream = zlib(&zeros);
+        assert!(zeros.len() > stream.len() * 900);
+        assert_eq!(zlib_inflate(&stream).unwrap(), zeros);
+        assert_eq!(zlib_inflate_prefix(&[stream.as_slice(), b"after"].concat()).unwrap(), zeros);
+
+        let text = b"a few words".repeat(1000);
+        assert_eq!(zlib_inflate(&zlib(&text)).unwrap(), text);
+    }
+
+    #[test]
+    fn sizes_from_headers_are_bounded_by_the_data() {
+        let stream = zlib(b"small");
+        assert_eq!(deflate_decompress(&stream[2..stream.len() - 4], usize::MAX).unwrap(), b"small");
+        assert_eq!(zlib_decompress(&stream, usize::MAX).unwrap(), b"small");
+        assert_eq!(zlib_decompress(&stream, 8).unwrap(), b"small");
+        assert!(matches!(zlib_decompress(&stream, 4), Err(Error::SizeMismatch { expected: 4, actual: 5 })));
+        assert!(matches!(zlib_decompress(b"\x78\x9cnot deflate", 4), Err(Error::Decompression(_))));
+        assert_eq!(max_inflated_size(&stream), stream.len() * MAX_DEFLATE_RATIO + 1024);
+    }
+}
@@ -1,278 +1,461 @@
-use std::cell::RefCell;
-use std::io::{Cursor, Write};
-use std::mem;
-use std::fs::{self, File};
-use std::io::{Seek, SeekFrom, Read, self};
+use std::io::{self, Write};
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::convert::TryInto;
-use libdeflater::Decompressor;
-use rayon::prelude::*;
-use binrw::BinRead;
-use clap::Parser;
+use std::process::ExitCode;
+use clap::{Parser, Subcommand};
 use std::time::Instant;
-use std::io::BufWriter;
 use mimalloc::MiMalloc;
-use memchr::memmem;
-use memmap2::Mmap;
 
-
-thread_local! {
-    static DECOMPRESSOR: RefCell<Decompressor> = RefCell::new(Decompressor::new());
-}
+use extractor::{Archive, Error, PyzCipher};
+use extractor::archive::{MagicSource, ARCHIVE_ITEM_DEPENDENCY, ARCHIVE_ITEM_PYZ, ARCHIVE_ITEM_SPLASH};
+use extractor::splash::Splash;
+use extractor::{extract, report, salvage, verify};
+use extractor::extract::ExtractOptions;
+use extractor::filter::{Filter, Glob, Pattern, Regex};
 
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-const ARCHIVE_ITEM_PYZ: u8             = b'z'; // zlib (pyz) - frozen Python code
-const ARCHIVE_ITEM_PYSOURCE: u8        = b's'; // Python script (v3)
-/*
-const ARCHIVE_ITEM_BINARY: u8          = b'b'; // binary
-const ARCHIVE_ITEM_DEPENDENCY: u8      = b'd'; // runtime option
-const ARCHIVE_ITEM_ZIPFILE: u8         = b'Z'; // zlib (pyz) - frozen Python code
-const ARCHIVE_ITEM_PYPACKAGE: u8       = b'M'; // Python package (__init__.py)
-const ARCHIVE_ITEM_PYMODULE: u8        = b'm'; // Python module
-const ARCHIVE_ITEM_DATA: u8            = b'x'; // data
-const ARCHIVE_ITEM_RUNTIME_OPTION: u8  = b'o'; // runtime option
-const ARCHIVE_ITEM_SPLASH: u8          = b'l'; // splash resources
-const ARCHIVE_ITEM_SYMLINK: u8         = b'n'; // symbolic link
-*/
-
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[command(author, version, about, args_conflicts_with_subcommands = true)]
 struct Args {
-    #[arg(short, long)]
-    input: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(short, long, required = true)]
+    input: Option<String>,
 
     #[arg(short, long, default_value = "")]
     output: String,
-}
 
+    /// AES key used to build the archive with `--key`, found automatically when omitted
+    #[arg(short, long)]
+    key: Option<String>,
 
-#[derive(Default, Debug)]
-#[allow(dead_code)]
-struct PyinstEntry {
-    size: u32,
-    offset: u32,
-    compressed_size: u32,
-    uncompressed_size: u32,
-    compression_flag: u8,
-    type_: u8,
-    name: String
-}
+    /// Print the table of contents instead of extracting
+    #[arg(short, long)]
+    list: bool,
 
-#[allow(dead_code)]
-#[derive(BinRead)]
-#[br(big)]
-struct PyinstHeader {
-    signature: [u8; 8],
-    package_size: u32,
-    toc_offset: u32,
-    toc_size: u32,
-    python_version: u32
-}
+    /// Print a JSON report of the archive and the extraction instead of the console output
+    #[arg(long)]
+    json: bool,
 
-#[allow(dead_code)]
-#[derive(BinRead)]
-#[br(little)]
-struct PyzHeader {
-    magic: [u8; 4],
-    version: [u8; 4],
-    toc_offset: u32
-}
+    /// When the archive cannot be parsed, recover the TOC records, PYZ archives and zlib streams left in the file
+    #[arg(long)]
+    salvage: bool,
 
-const PYINST_MAGIC_BASE: [u8; 8] = [
-    b'M', b'E', b'I', 0x0C,
-    0x0B, 0x0A, 0x0B, 0x0E
-];
+    /// Expand zip entries such as `base_library.zip` into `<name>_extracted`
+    #[arg(long)]
+    expand_zip: bool,
 
-fn write_nested_file(base_path: &Path, entry: &PyinstEntry, file_content: &[u8], pyc_magic: [u8; 16]) -> io::Result<()> {
+    /// Write symlink entries as files holding their target instead of creating links
+    #[arg(long)]
+    symlinks_as_files: bool,
 
-    let full_path = if entry.name.contains('\\') {
-        base_path.join(entry.name.replace("\\", "/"))
-    } else {
-        base_path.join(&entry.name)
-    };
+    /// Write a bytecode listing (`.dis`) next to every script and PYZ module
+    #[arg(long)]
+    disassemble: bool,
 
-    if full_path.exists() {
-        return Ok(());
-    }
+    /// Sibling executable of a multipackage bundle, searched next to the input when not given, can be repeated
+    #[arg(long, value_name = "FILE")]
+    dependency: Vec<PathBuf>,
+
+    /// Extract every archive found in the file, including those nested in binary entries, into `archive_<n>`
+    #[arg(short, long)]
+    all: bool,
 
-    let content = &file_content[entry.offset as usize .. (entry.offset + entry.compressed_size) as usize];
+    /// Only extract entries and PYZ members matching this glob, can be repeated
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
 
-    if let Some(parent) = full_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
+    /// Skip entries and PYZ members matching this glob, can be repeated
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
 
-    let file = fs::File::create(&full_path)?;
-    let mut writer = BufWriter::new(file);
+    /// Only extract entries and PYZ members matching this regex, can be repeated
+    #[arg(long, value_name = "REGEX")]
+    include_regex: Vec<String>,
 
-    if entry.compression_flag == 1 {
-        let mut output = vec![0u8; entry.uncompressed_size as usize];
-        DECOMPRESSOR.with(|decompressor| {
-            let mut decompressor = decompressor.borrow_mut();
-            decompressor.zlib_decompress(content, &mut output).expect("Decompression failed");
-        });
+    /// Skip entries and PYZ members matching this regex, can be repeated
+    #[arg(long, value_name = "REGEX")]
+    exclude_regex: Vec<String>,
 
-        if pyc_magic[0] != 0 && entry.type_ == ARCHIVE_ITEM_PYSOURCE {
-            writer.write_all(&pyc_magic)?;
+    /// Only extract these type codes, e.g. `sb` (PYZ members are `m`, `M` or `x`)
+    #[arg(long = "type", value_name = "CODES")]
+    types: Option<String>,
+}
+
+impl Args {
+    fn output_path(&self, input: &str) -> PathBuf {
+        if self.output.is_empty() {
+            PathBuf::from(format!("{}_extracted", input))
+        } else {
+            PathBuf::from(&self.output)
         }
-        writer.write_all(&output)?;
+    }
 
-    } else {
-        writer.write_all(content)?;
+    fn filter(&self) -> extractor::Result<Filter> {
+        let patterns = |globs: &[String], regexes: &[String]| -> extractor::Result<Vec<Pattern>> {
+            let mut patterns = Vec::new();
+            for glob in globs {
+                patterns.push(Pattern::Glob(Glob::new(glob)?));
+            }
+            for regex in regexes {
+                patterns.push(Pattern::Regex(Regex::new(regex)?));
+            }
+            Ok(patterns)
+        };
+
+        Ok(Filter {
+            include: patterns(&self.include, &self.include_regex)?,
+            exclude: patterns(&self.exclude, &self.exclude_regex)?,
+            types: self.types.as_ref().map(|types| types.bytes().collect()),
+        })
     }
+}
 
-    writer.flush()?;
-    Ok(())
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the table of contents without extracting
+    List {
+        input: String,
+
+        /// Print the structure as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Decompress every entry and PYZ member and check them against the TOC without writing anything
+    Verify {
+        input: String,
+
+        /// AES key used to build the archive with `--key`, found automatically when omitted
+        #[arg(short, long)]
+        key: Option<String>,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a single TOC entry or PYZ member to stdout
+    Cat {
+        input: String,
+
+        /// TOC name, PYZ member name (`app.main`) or member path (`app/main.pyc`)
+        name: String,
+
+        /// AES key used to build the archive with `--key`, found automatically when omitted
+        #[arg(short, long)]
+        key: Option<String>,
+    },
 }
 
-fn parse_entry(fp: &mut File, overlay_offset: usize) -> PyinstEntry {
+// Console output, silenced when stdout carries the JSON report
+macro_rules! status {
+    ($json:expr, $($arg:tt)*) => {
+        if !$json {
+            println!($($arg)*);
+        }
+    };
+}
 
-    // not using binrw cause idk how to parse null-terminated dynamic sized strings
+// Exit codes: 1 when the archive cannot be parsed, 2 when some entries could not be extracted
+fn main() -> ExitCode {
+    let args = Args::parse();
 
-    let mut buffer = [0u8; 18];
-    fp.read_exact(&mut buffer).expect("Read error");
+    let result = match args.command {
+        Some(Command::List { ref input, json }) => run_list(input, json),
+        Some(Command::Verify { ref input, ref key, json }) => run_verify(input, key.clone(), json),
+        Some(Command::Cat { ref input, ref name, ref key }) => run_cat(input, name, key.clone()),
+        None if args.list => run_list(args.input.as_deref().unwrap_or_default(), args.json),
+        None if args.all => run_all(args),
+        None => run(args),
+    };
 
-    let size = u32::from_be_bytes(buffer[0..4].try_into().unwrap());
-    let offset = u32::from_be_bytes(buffer[4..8].try_into().unwrap()) + overlay_offset as u32;
-    let compressed_size = u32::from_be_bytes(buffer[8..12].try_into().unwrap());
-    let uncompressed_size = u32::from_be_bytes(buffer[12..16].try_into().unwrap());
-    let compression_flag = buffer[16];
-    let type_ = buffer[17];
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(2),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    // name_size = TotalSize - ((Size) Size + (Offset) Size + (CompressedSize) Size + (UncompressedSize) Size + (CompressionFlag) Size + (type) Size)
-    let name_size = size - (4 * 4 + 1 + 1);
+fn run_list(input: &str, json: bool) -> extractor::Result<bool> {
+    let archive = Archive::open(input)?;
 
-    let mut buffer: Vec<u8> = vec![0u8; name_size as usize];
+    if json {
+        println!("{}", report::archive_json(&archive, None));
+        return Ok(true);
+    }
+
+    println!("{:<12} {:<4} {:<10} {:<4} {:>12} {:>12}  Name", "Offset", "Type", "", "Flag", "Compressed", "Uncompressed");
+
+    let mut ok = true;
 
-    fp.read_exact(&mut buffer).expect("Read error");
-    if let Some(pos) = buffer.iter().position(|&b| b == 0) {
-        buffer.truncate(pos);
+    for entry in archive.entries() {
+        println!("{:#012X} {:<4} {:<10} {:<4} {:>12} {:>12}  {}",
+            entry.offset, entry.type_ as char, entry.type_name(), entry.compression_flag,
+            entry.compressed_size, entry.uncompressed_size, entry.name);
+
+        if entry.type_ == ARCHIVE_ITEM_PYZ {
+            match archive.pyz(entry) {
+                Ok(pyz) => for member in pyz.members() {
+                    println!("{:#012X} {:<4} {:<10} {:<4} {:>12} {:>12}    {}",
+                        entry.offset + member.offset as u64, member.type_, member.type_name(), "",
+                        member.size, "", member.name);
+                },
+                Err(e) => {
+                    println!("  Cannot read {}: {}", entry.name, e);
+                    ok = false;
+                }
+            }
+        }
     }
-    
-    let mut name = String::from_utf8(buffer).expect("Name error");
 
-    if type_ == ARCHIVE_ITEM_PYSOURCE {
-        name.push_str(".pyc");
+    println!("{} entries", archive.entries().len());
+
+    Ok(ok)
+}
+
+fn run_verify(input: &str, key: Option<String>, json: bool) -> extractor::Result<bool> {
+    let start = Instant::now();
+    let archive = Archive::open(input)?;
+
+    let key = key.or_else(|| archive.crypto_key());
+    let cipher = key.as_deref().and_then(PyzCipher::new);
+
+    let report = verify::verify(&archive, cipher.as_ref());
+
+    if json {
+        println!("{}", report::verify_json(&report));
+        return Ok(report.is_ok());
     }
 
-    PyinstEntry { 
-        size: size,
-        offset: offset,
-        compressed_size: compressed_size,
-        uncompressed_size: uncompressed_size,
-        compression_flag: compression_flag,
-        type_: type_,
-        name: name
+    for (name, e) in &report.issues {
+        println!("{:?}: {}", name, e);
     }
+    println!("Checked {} entries and {} PYZ members in {} ms: {} issues",
+        report.entries, report.members, start.elapsed().as_millis(), report.issues.len());
 
+    Ok(report.is_ok())
 }
 
-fn parse_header(fp: &mut File, header_offset: usize) -> PyinstHeader {
+fn run_cat(input: &str, name: &str, key: Option<String>) -> extractor::Result<bool> {
+    let archive = Archive::open(input)?;
+
+    let key = key.or_else(|| archive.crypto_key());
+    let options = ExtractOptions {
+        pyc_header: archive.pyc_header(),
+        cipher: key.as_deref().and_then(PyzCipher::new),
+        ..Default::default()
+    };
 
-    fp.seek(SeekFrom::Start(header_offset as u64)).expect("Cannot seek to header");
-    let header = PyinstHeader::read(fp).expect("Error reading header");
-    fp.rewind().expect("Rewind error");
+    let contents = extract::read_named(&archive, name, &options)?;
 
-    header
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&contents)?;
+    stdout.flush()?;
+
+    Ok(true)
 }
 
-fn main() -> io::Result<()> {
-    let start = Instant::now();
+fn run_all(args: Args) -> extractor::Result<bool> {
+    let filter = args.filter()?;
+    let input = args.input.clone().unwrap_or_default();
+    let base_path = args.output_path(&input);
+    let json = args.json;
 
-    let args = Args::parse();
+    status!(json, "Extracting every archive in {}", input);
 
-    println!("Extracting {}", args.input);
+    let start = Instant::now();
 
-    let mut base_path = PathBuf::new();
+    // Fails the same way as a single extraction when there is no archive at all
+    let archive = Archive::open(&input)?;
+    let options = ExtractOptions {
+        cipher: args.key.as_deref().and_then(PyzCipher::new),
+        filter,
+        expand_zips: args.expand_zip,
+        symlinks_as_files: args.symlinks_as_files,
+        disassemble: args.disassemble,
+        ..Default::default()
+    };
+    let archives = extract::extract_embedded(&base_path, archive.data(), &options);
 
-    if args.output.len() > 0 {
-        base_path.push(args.output);
-    } else {
-        base_path.push(format!("{}_extracted", args.input));
+    for found in &archives {
+        status!(json, "Archive at {} ({} entries): extracted {} files as {}",
+            found.location, found.entries, found.report.written, found.output.display());
+        for (name, e) in &found.report.failures {
+            status!(json, "  {:?}: {}", name, e);
+        }
+        if !found.report.encrypted.is_empty() {
+            status!(json, "  Could not decrypt {} PYZ members (kept as .encrypted)", found.report.encrypted.len());
+        }
     }
 
-    let mut fp = File::open(args.input)?;
+    status!(json, "Found {} archives", archives.len());
+    status!(json, "Extraction took: {} ms", start.elapsed().as_millis());
 
+    if json {
+        println!("{}", report::embedded_json(&archives));
+    }
 
-    let mmap = unsafe { Mmap::map(&fp)? };
-    let filesize = mmap.len() as usize;
-    
+    Ok(archives.iter().all(|found| found.report.is_ok()))
+}
 
-    let signature: &[u8] = &PYINST_MAGIC_BASE;
+fn run_salvage(input: &str, base_path: &Path, options: ExtractOptions, json: bool, error: Error) -> extractor::Result<bool> {
+    let start = Instant::now();
+    let data = fs::read(input)?;
 
-    let search_len = 1 * 1024 * 1024;
-    let sstart = mmap.len().saturating_sub(search_len);
-    let slice = &mmap[sstart..];
-    let rel_offset  = memmem::find(&slice, signature).expect("Invalid pyinstaller archive");
-    
-    let offset = sstart + rel_offset;
-    
-    println!("Got header offset at: {:#2X}", offset);
- 
-  
-    // To count useless tail Bytes 
-    fp.seek(SeekFrom::Start((offset + mem::size_of::<PyinstHeader>()) as u64)).expect("Seek error"); 
-    
-    let mut buffer: Vec<u8> = Vec::new();
+    let report = salvage::salvage(base_path, &data, options);
 
-    fp.read_to_end(&mut buffer).expect("Cannot read file");
-    let tail_size = buffer.len();
+    status!(json, "Recovered {} TOC entries and {} PYZ archives, carved {} streams",
+        report.toc_entries, report.pyz, report.carved);
+    status!(json, "Extracted {} files as: {}", report.extraction.written, base_path.display());
+    for (name, e) in &report.extraction.failures {
+        status!(json, "  {:?}: {}", name, e);
+    }
+    status!(json, "Salvage took: {} ms", start.elapsed().as_millis());
 
-    fp.rewind().expect("Rewind error");
+    if json {
+        println!("{}", report::salvage_json(&report));
+    }
 
-    let header = parse_header(&mut fp, offset);
-    
-    let overlay_offset = filesize - header.package_size as usize - tail_size;
+    // Nothing at all could be recovered, report why the archive did not parse
+    if report.extraction.written == 0 && report.extraction.failures.is_empty() {
+        return Err(error);
+    }
 
-    println!("Package Size: {}\nToc Size: {}\nToc Offset: {:#2X}\nPython Version: {}.{}", header.package_size, header.toc_size, header.toc_offset,(header.python_version/100) as i32, (header.python_version%100) as i32);
+    // The archive is damaged, a salvage is never a complete extraction
+    Ok(false)
+}
 
-    
-    fp.seek(SeekFrom::Start(overlay_offset as u64 + header.toc_offset as u64 + 64)).expect("Seek error"); 
+fn run(args: Args) -> extractor::Result<bool> {
+    let start = Instant::now();
 
-    let mut bytes_read = 0;
+    let filter = args.filter()?;
+    let input = args.input.clone().unwrap_or_default();
+    let json = args.json;
 
-    let mut toc: Vec<PyinstEntry> = Vec::new();
+    status!(json, "Extracting {}", input);
 
-    let mut entry: PyinstEntry;
+    let base_path = args.output_path(&input);
 
-    let mut pyc_magic = [0u8; 16];
-    
+    let archive = match Archive::open(&input) {
+        Ok(archive) => archive,
+        Err(e) if args.salvage && !matches!(e, Error::Io(_)) => {
+            status!(json, "{}, salvaging", e);
+            let options = ExtractOptions { cipher: args.key.as_deref().and_then(PyzCipher::new), filter, ..Default::default() };
+            return run_salvage(&input, &base_path, options, json, e);
+        }
+        Err(e) => return Err(e),
+    };
+    let header = archive.header();
 
-    
-    while bytes_read < header.toc_size {
-        entry = parse_entry(&mut fp, overlay_offset + 64);
+    status!(json, "Got header offset at: {:#2X}", archive.cookie_offset());
+    if archive.magic_modified() {
+        status!(json, "Cookie magic was modified: {}", report::hex(&header.signature));
+    }
 
-        if entry.type_ == ARCHIVE_ITEM_PYZ {
-            let mut content = Cursor::new(&mmap[entry.offset as usize .. (entry.offset + entry.compressed_size) as usize]);
+    status!(json, "Cookie Layout: {:?}\nOverlay Offset: {:#2X}\nTail Size: {}", header.layout, archive.overlay_offset(), archive.tail_size());
+    status!(json, "Package Size: {}\nToc Size: {}\nToc Offset: {:#2X}", header.package_size, header.toc_size, header.toc_offset);
+    match header.python_version() {
+        Some(version) => status!(json, "Python Version: {}", version),
+        None => status!(json, "Python Version: unknown ({})", header.python_version),
+    }
+    if let Some(pylib_name) = &header.pylib_name {
+        status!(json, "Python Library: {}", pylib_name);
+    }
 
-            let pyz_header = PyzHeader::read(&mut content).expect("Invalid pyz header");
+    let runtime_options = archive.runtime_options();
+    if !runtime_options.is_empty() {
+        status!(json, "Runtime Options:");
+    }
+    for option in &runtime_options {
+        let value = option.value.as_deref().map(|value| format!(" {}", value)).unwrap_or_default();
+        let description = option.describe().map(|description| format!(", {}", description)).unwrap_or_default();
+        status!(json, "  {}{} ({}{})", option.name, value, option.kind.describe(), description);
+    }
 
-            pyc_magic[..4].copy_from_slice(&pyz_header.version);
+    for entry in archive.entries().iter().filter(|entry| entry.type_ == ARCHIVE_ITEM_SPLASH) {
+        match archive.read_entry(entry).and_then(|data| Splash::parse(&data)) {
+            Ok(splash) => status!(json, "Splash Screen: {} image ({} bytes), {} / {}, {} required files",
+                splash.image_extension(), splash.image.len(), splash.tcl_libname, splash.tk_libname, splash.requirements.len()),
+            Err(e) => status!(json, "Splash Screen: {}", e),
         }
-
-        bytes_read += entry.size;
-        toc.push(entry);
     }
 
-    println!("Parsed{} entries", toc.len());
+    let toc = archive.entries();
+
+    status!(json, "Parsed{} entries", toc.len());
     let duration = start.elapsed();
-    println!("Parsing took: {} ms", duration.as_millis());
-    if pyc_magic[0] == 0 {
-        println!("Cannot find the python header...");
+    status!(json, "Parsing took: {} ms", duration.as_millis());
+
+    match archive.pyc_magic() {
+        Some((_, MagicSource::Pyz)) => {}
+        Some((magic, source)) => status!(json, "Inferred pyc magic {:02X?} from {}", magic, source.describe()),
+        None => status!(json, "Cannot find the python header..."),
     }
+    let pyc_header = archive.pyc_header();
+
+    let key = args.key.or_else(|| archive.crypto_key());
+    if let Some(key) = &key {
+        status!(json, "Crypto Key: {}", key);
+    }
+    let cipher = key.as_deref().and_then(PyzCipher::new);
+
     let start = Instant::now();
 
-    toc.par_iter().for_each(|entry|  {
-        write_nested_file(base_path.as_path(), entry, &mmap, pyc_magic).expect("Write error");
-    });
+    let options = ExtractOptions {
+        pyc_header,
+        cipher,
+        filter,
+        expand_zips: args.expand_zip,
+        symlinks_as_files: args.symlinks_as_files,
+        disassemble: args.disassemble,
+    };
+    if options.disassemble && options.disassembly_version().is_none() {
+        status!(json, "Cannot disassemble this python version, only 2.7 and 3.6 to 3.13 are supported");
+    }
+    let mut report = extract::extract_all(base_path.as_path(), &archive, &options);
 
-    println!("Extracted as: {}", base_path.to_str().expect("!"));
+    // Multipackage bundles store some files in sibling executables
+    if archive.entries().iter().any(|entry| entry.type_ == ARCHIVE_ITEM_DEPENDENCY) {
+        let dependencies = extract::extract_dependencies(&base_path, Path::new(&input), &archive, &options, &args.dependency);
+        status!(json, "Extracted {} files from sibling executables", dependencies.written);
+        report.merge(dependencies);
+    }
+
+    status!(json, "Extracted {} files as: {}", report.written, base_path.display());
+    if !options.filter.is_empty() {
+        status!(json, "Skipped {} entries not matching the filter", report.skipped.len());
+    }
 
     let duration = start.elapsed();
-    println!("Extraction took: {} ms", duration.as_millis());
+    status!(json, "Extraction took: {} ms", duration.as_millis());
+
+    if !report.encrypted.is_empty() {
+        report.encrypted.sort_unstable();
+        status!(json, "Could not decrypt {} PYZ members (kept as .encrypted):", report.encrypted.len());
+        for name in &report.encrypted {
+            status!(json, "  {}", name);
+        }
+    }
+
+    if !report.failures.is_empty() {
+        report.failures.sort_by(|a, b| a.0.cmp(&b.0));
+        status!(json, "Failed to extract {} entries:", report.failures.len());
+        for (name, e) in &report.failures {
+            status!(json, "  {:?}: {}", name, e);
+        }
+    }
+
+    if json {
+        let mut value = report::archive_json(&archive, Some(&report));
+        value.insert("crypto_key", key.into());
+        println!("{}", value);
+    }
 
-    Ok(())
+    Ok(report.is_ok())
 }
- 
\ No newline at end of file
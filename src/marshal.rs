@@ -0,0 +1,910 @@
+// Python `marshal` deserializer: PYZ tables of contents, code objects and scripts are all marshalled
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::pyc::{self, PythonVersion};
+
+// Marshal type codes, the high bit (FLAG_REF) marks objects stored in the ref table
+const FLAG_REF: u8          = 0x80;
+
+const TYPE_NULL: u8         = b'0';
+const TYPE_NONE: u8         = b'N';
+const TYPE_FALSE: u8        = b'F';
+const TYPE_TRUE: u8         = b'T';
+const TYPE_STOPITER: u8     = b'S';
+const TYPE_ELLIPSIS: u8     = b'.';
+const TYPE_INT: u8          = b'i';
+const TYPE_INT64: u8        = b'I';
+const TYPE_FLOAT: u8        = b'f';
+const TYPE_BINARY_FLOAT: u8 = b'g';
+const TYPE_COMPLEX: u8      = b'x';
+const TYPE_BINARY_COMPLEX: u8 = b'y';
+const TYPE_LONG: u8         = b'l';
+const TYPE_STRING: u8       = b's';
+const TYPE_INTERNED: u8     = b't';
+const TYPE_STRINGREF: u8    = b'R';
+const TYPE_REF: u8          = b'r';
+const TYPE_TUPLE: u8        = b'(';
+const TYPE_LIST: u8         = b'[';
+const TYPE_DICT: u8         = b'{';
+const TYPE_CODE: u8         = b'c';
+const TYPE_UNICODE: u8      = b'u';
+const TYPE_SET: u8          = b'<';
+const TYPE_FROZENSET: u8    = b'>';
+const TYPE_ASCII: u8        = b'a';
+const TYPE_ASCII_INTERNED: u8 = b'A';
+const TYPE_SMALL_TUPLE: u8  = b')';
+const TYPE_SHORT_ASCII: u8  = b'z';
+const TYPE_SHORT_ASCII_INTERNED: u8 = b'Z';
+const TYPE_SLICE: u8        = b':';
+
+// Back-references are copies: a few bytes of refs to refs, or many refs to one long string, would
+// expand far beyond the data. The values read and copied are limited to this many per byte of data,
+// compiled code stays under 2
+const EXPANSION: usize      = 8;
+// Allowance on top of `EXPANSION` for small data
+const MIN_BUDGET: usize     = 1 << 20;
+
+// Deeper data is refused instead of overflowing the stack. CPython allows 2000 but the listings
+// are written from worker threads with 2 MB stacks, where a level takes up to 3 KB in debug builds.
+// Python source cannot nest more than 200 parentheses nor 100 indents
+const MAX_DEPTH: usize      = 500;
+
+// Kinds of the 3.11+ `co_localspluskinds`
+const CO_FAST_LOCAL: u8     = 0x20;
+const CO_FAST_CELL: u8      = 0x40;
+const CO_FAST_FREE: u8      = 0x80;
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Null,
+    None,
+    Bool(bool),
+    StopIteration,
+    Ellipsis,
+    Int(i64),
+    /// Integer that does not fit in an `i64` or python 2 `long`, as decimal text
+    Long(String),
+    Float(f64),
+    Complex(f64, f64),
+    Bytes(Vec<u8>),
+    Str(String),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Dict(Vec<(Value, Value)>),
+    Set(Vec<Value>),
+    FrozenSet(Vec<Value>),
+    /// 3.14+ constant slices as (start, stop, step)
+    Slice(Box<(Value, Value, Value)>),
+    Code(Box<Code>),
+}
+
+/// A code object, the fields a version does not store are left empty
+#[derive(Debug, Clone, Default)]
+pub struct Code {
+    pub argcount: u32,
+    pub posonlyargcount: u32,
+    pub kwonlyargcount: u32,
+    pub nlocals: u32,
+    pub stacksize: u32,
+    pub flags: u32,
+    pub code: Vec<u8>,
+    pub consts: Vec<Value>,
+    pub names: Vec<String>,
+    pub varnames: Vec<String>,
+    pub freevars: Vec<String>,
+    pub cellvars: Vec<String>,
+    /// Locals, cells and free variables in the order the 3.11+ opcodes index them
+    pub localsplusnames: Vec<String>,
+    pub localspluskinds: Vec<u8>,
+    pub filename: String,
+    pub name: String,
+    /// 3.11+
+    pub qualname: Option<String>,
+    pub firstlineno: u32,
+    /// `co_lnotab` before 3.10, `co_linetable` after
+    pub linetable: Vec<u8>,
+    /// 3.11+
+    pub exceptiontable: Vec<u8>,
+}
+
+// Python float repr: the shortest text that reads back, with an exponent below 1e-4 and from 1e16
+fn float_repr(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value < 0.0 { "-inf" } else { "inf" }.to_string();
+    }
+
+    let text = format!("{:?}", value);
+    match text.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let (sign, digits) = match exponent.strip_prefix('-') {
+                Some(digits) => ('-', digits),
+                None => ('+', exponent),
+            };
+            format!("{}e{}{:0>2}", mantissa, sign, digits)
+        }
+        None => text,
+    }
+}
+
+fn quote(contains: impl Fn(char) -> bool) -> char {
+    if contains('\'') && !contains('"') { '"' } else { '\'' }
+}
+
+fn str_repr(text: &str) -> String {
+    let quote = quote(|c| text.contains(c));
+    let mut repr = String::from(quote);
+
+    for c in text.chars() {
+        match c {
+            '\\' => repr.push_str("\\\\"),
+            '\n' => repr.push_str("\\n"),
+            '\r' => repr.push_str("\\r"),
+            '\t' => repr.push_str("\\t"),
+            c if c == quote => {
+                repr.push('\\');
+                repr.push(c);
+            }
+            c if c.is_control() || (c.is_whitespace() && c != ' ') => match c as u32 {
+                code @ 0..=0xFF => repr.push_str(&format!("\\x{:02x}", code)),
+                code @ 0x100..=0xFFFF => repr.push_str(&format!("\\u{:04x}", code)),
+                code => repr.push_str(&format!("\\U{:08x}", code)),
+            },
+            c => repr.push(c),
+        }
+    }
+
+    repr.push(quote);
+    repr
+}
+
+fn bytes_repr(bytes: &[u8]) -> String {
+    let quote = quote(|c| bytes.contains(&(c as u8)));
+    let mut repr = format!("b{}", quote);
+
+    for &b in bytes {
+        match b {
+            b'\\' => repr.push_str("\\\\"),
+            b'\n' => repr.push_str("\\n"),
+            b'\r' => repr.push_str("\\r"),
+            b'\t' => repr.push_str("\\t"),
+            b if b == quote as u8 => {
+                repr.push('\\');
+                repr.push(b as char);
+            }
+            0x20..=0x7E => repr.push(b as char),
+            b => repr.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+
+    repr.push(quote);
+    repr
+}
+
+// Python 2 `unicode`, escaped like `str` but every character outside printable ASCII is escaped too
+fn unicode_repr(text: &str) -> String {
+    let quote = quote(|c| text.contains(c));
+    let mut repr = format!("u{}", quote);
+
+    for c in text.chars() {
+        match c {
+            '\\' => repr.push_str("\\\\"),
+            '\n' => repr.push_str("\\n"),
+            '\r' => repr.push_str("\\r"),
+            '\t' => repr.push_str("\\t"),
+            c if c == quote => {
+                repr.push('\\');
+                repr.push(c);
+            }
+            ' '..='~' => repr.push(c),
+            c => match c as u32 {
+                code @ 0..=0xFF => repr.push_str(&format!("\\x{:02x}", code)),
+                code @ 0x100..=0xFFFF => repr.push_str(&format!("\\u{:04x}", code)),
+                code => repr.push_str(&format!("\\U{:08x}", code)),
+            },
+        }
+    }
+
+    repr.push(quote);
+    repr
+}
+
+fn join(values: &[Value], python2: bool) -> String {
+    values.iter().map(|value| value.repr_in(python2)).collect::<Vec<_>>().join(", ")
+}
+
+fn dict_repr(items: &[(Value, Value)], python2: bool) -> String {
+    let items: Vec<String> = items.iter()
+        .map(|(key, value)| format!("{}: {}", key.repr_in(python2), value.repr_in(python2)))
+        .collect();
+    format!("{{{}}}", items.join(", "))
+}
+
+impl Value {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(v) => Some(*v),
+            Value::Long(text) => text.parse().ok(),
+            Value::Bool(v) => Some(*v as i64),
+            _ => None,
+        }
+    }
+
+    // PYZ names are str in python 3 and bytes in python 2
+    pub fn as_str(&self) -> Option<String> {
+        match self {
+            Value::Str(s) => Some(s.clone()),
+            Value::Bytes(b) => Some(String::from_utf8_lossy(b).into_owned()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Items of a tuple or a list
+    pub fn as_seq(&self) -> Option<&[Value]> {
+        match self {
+            Value::Tuple(items) | Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_code(&self) -> Option<&Code> {
+        match self {
+            Value::Code(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Value of a dict for a string key
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(items) => items.iter()
+                .find(|(k, _)| k.as_str().as_deref() == Some(key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// The text python's `repr` gives for the value, code objects are shown with their name and position
+    pub fn repr(&self) -> String {
+        self.repr_in(false)
+    }
+
+    /// The text python 2's `repr` gives, where `Bytes` are `str`, `Str` is `unicode` and big integers are `long`
+    pub fn repr_python2(&self) -> String {
+        self.repr_in(true)
+    }
+
+    fn repr_in(&self, python2: bool) -> String {
+        // Only containers recurse, the frames of the recursion must stay small (see `Reader::read_value`)
+        match self {
+            Value::Tuple(items) if items.len() == 1 => format!("({},)", items[0].repr_in(python2)),
+            Value::Tuple(items) => format!("({})", join(items, python2)),
+            Value::List(items) => format!("[{}]", join(items, python2)),
+            Value::Dict(items) => dict_repr(items, python2),
+            Value::Set(items) if python2 => format!("set([{}])", join(items, python2)),
+            Value::Set(items) if items.is_empty() => "set()".to_string(),
+            Value::Set(items) => format!("{{{}}}", join(items, python2)),
+            Value::FrozenSet(items) if python2 => format!("frozenset([{}])", join(items, python2)),
+            Value::FrozenSet(items) if items.is_empty() => "frozenset()".to_string(),
+            Value::FrozenSet(items) => format!("frozenset({{{}}})", join(items, python2)),
+            Value::Slice(slice) => {
+                format!("slice({}, {}, {})", slice.0.repr_in(python2), slice.1.repr_in(python2), slice.2.repr_in(python2))
+            }
+            _ => self.scalar_repr(python2),
+        }
+    }
+
+    fn scalar_repr(&self, python2: bool) -> String {
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::None => "None".to_string(),
+            Value::Bool(true) => "True".to_string(),
+            Value::Bool(false) => "False".to_string(),
+            Value::StopIteration => "StopIteration".to_string(),
+            Value::Ellipsis => "Ellipsis".to_string(),
+            Value::Int(value) => value.to_string(),
+            Value::Long(text) if python2 => format!("{}L", text),
+            Value::Long(text) => text.clone(),
+            Value::Float(value) => float_repr(*value),
+            Value::Complex(real, imag) if *real == 0.0 && real.is_sign_positive() => format!("{}j", float_repr(*imag).trim_end_matches(".0")),
+            Value::Complex(real, imag) => {
+                let imag_text = float_repr(*imag);
+                let sign = if imag_text.starts_with('-') { "" } else { "+" };
+                format!("({}{}{}j)", float_repr(*real).trim_end_matches(".0"), sign, imag_text.trim_end_matches(".0"))
+            }
+            Value::Bytes(bytes) if python2 => bytes_repr(bytes)[1..].to_string(),
+            Value::Bytes(bytes) => bytes_repr(bytes),
+            Value::Str(text) if python2 => unicode_repr(text),
+            Value::Str(text) => str_repr(text),
+            Value::Code(code) => format!("<code object {}, file {:?}, line {}>", code.name, code.filename, code.firstlineno),
+            _ => unreachable!("containers are written by `repr`"),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.repr())
+    }
+}
+
+impl Code {
+    /// Code objects among the constants, functions, classes and comprehensions defined in this one
+    pub fn children(&self) -> impl Iterator<Item = &Code> {
+        self.consts.iter().filter_map(Value::as_code)
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::Marshal(msg.to_string())
+}
+
+fn strings(value: Value) -> Result<Vec<String>> {
+    match value {
+        Value::Tuple(items) | Value::List(items) => items.iter()
+            .map(|item| item.as_str().ok_or_else(|| invalid("expected a tuple of strings")))
+            .collect(),
+        _ => Err(invalid("expected a tuple of strings")),
+    }
+}
+
+fn string(value: Value) -> Result<String> {
+    value.as_str().ok_or_else(|| invalid("expected a string"))
+}
+
+fn bytes(value: Value) -> Result<Vec<u8>> {
+    match value {
+        Value::Bytes(b) => Ok(b),
+        _ => Err(invalid("expected bytes")),
+    }
+}
+
+// Sign of the size, then 15 bit digits with the least significant first
+fn long_text(negative: bool, digits: &[u16]) -> String {
+    let mut digits = digits.to_vec();
+    let mut chunks = Vec::new();
+
+    // Repeated division by 10^4, the remainders are the decimal digits from the end
+    while digits.iter().any(|&d| d != 0) {
+        let mut remainder = 0u32;
+        for digit in digits.iter_mut().rev() {
+            let value = (remainder << 15) | *digit as u32;
+            *digit = (value / 10_000) as u16;
+            remainder = value % 10_000;
+        }
+        chunks.push(remainder);
+    }
+
+    let mut text = String::from(if negative { "-" } else { "" });
+    match chunks.split_last() {
+        Some((first, rest)) => {
+            text.push_str(&first.to_string());
+            for chunk in rest.iter().rev() {
+                text.push_str(&format!("{:04}", chunk));
+            }
+        }
+        None => text.push('0'),
+    }
+    text
+}
+
+// Fields of a code object from the objects marshalled before and after `co_firstlineno`
+fn fill_code(code: &mut Code, version: (u8, u8), objects: Vec<Value>, tables: Vec<Value>) -> Result<()> {
+    let mut objects = objects.into_iter();
+    let mut next = || objects.next().unwrap();
+
+    code.code = bytes(next())?;
+    code.consts = match next() {
+        Value::Tuple(items) => items,
+        _ => return Err(invalid("expected a tuple of constants")),
+    };
+    code.names = strings(next())?;
+
+    if version < (3, 11) {
+        code.varnames = strings(next())?;
+        code.freevars = strings(next())?;
+        code.cellvars = strings(next())?;
+    } else {
+        code.localsplusnames = strings(next())?;
+        code.localspluskinds = bytes(next())?;
+
+        if code.localsplusnames.len() != code.localspluskinds.len() {
+            return Err(invalid("localsplus names and kinds differ in length"));
+        }
+
+        let with_kind = |kind: u8| -> Vec<String> {
+            code.localsplusnames.iter().zip(&code.localspluskinds)
+                .filter(|&(_, &k)| k & kind != 0)
+                .map(|(name, _)| name.clone())
+                .collect()
+        };
+        code.varnames = with_kind(CO_FAST_LOCAL);
+        code.cellvars = with_kind(CO_FAST_CELL);
+        code.freevars = with_kind(CO_FAST_FREE);
+        code.nlocals = code.varnames.len() as u32;
+    }
+
+    code.filename = string(next())?;
+    code.name = string(next())?;
+    if version >= (3, 11) {
+        code.qualname = Some(string(next())?);
+    }
+
+    let mut tables = tables.into_iter();
+    code.linetable = bytes(tables.next().unwrap())?;
+    if version >= (3, 11) {
+        code.exceptiontable = bytes(tables.next().unwrap())?;
+    }
+
+    Ok(())
+}
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// With the weight of each value, see `produced`
+    refs: Vec<Option<(Value, usize)>>,
+    /// Needed for code objects, their fields depend on the version
+    version: Option<PythonVersion>,
+    /// Python 2 interned strings, referenced by `TYPE_STRINGREF`
+    interned: Vec<Value>,
+    depth: usize,
+    /// Weight of everything read so far, one per value plus the length of strings and longs, copies included
+    produced: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0, refs: Vec::new(), version: None, interned: Vec::new(), depth: 0, produced: 0 }
+    }
+
+    /// Reader for data that may hold code objects marshalled by the given python version
+    pub fn with_version(data: &'a [u8], version: PythonVersion) -> Self {
+        Reader { version: Some(version), ..Reader::new(data) }
+    }
+
+    // Counts `weight` more values read or copied, refused once past the budget of the data
+    fn produce(&mut self, weight: usize) -> Result<()> {
+        self.produced = self.produced.saturating_add(weight);
+        if self.produced > self.data.len().saturating_mul(EXPANSION).saturating_add(MIN_BUDGET) {
+            return Err(invalid("references expand beyond the size of the data"));
+        }
+        Ok(())
+    }
+
+    fn is_python2(&self) -> bool {
+        self.version.is_some_and(|version| version.major == 2)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.data.len()).ok_or_else(|| invalid("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let len = self.read_i32()?;
+        if len < 0 {
+            return Err(invalid("negative length"));
+        }
+        Ok(len as usize)
+    }
+
+    fn read_str(&mut self, len: usize) -> Result<String> {
+        let bytes = self.read_bytes(len)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    // Old text format of floats, a length byte then the repr
+    fn read_float_text(&mut self) -> Result<f64> {
+        let len = self.read_u8()? as usize;
+        let text = self.read_str(len)?;
+        text.trim().parse().map_err(|_| invalid(&format!("bad float {:?}", text)))
+    }
+
+    fn read_long(&mut self) -> Result<Value> {
+        let size = self.read_i32()?;
+        let count = size.unsigned_abs() as usize;
+
+        let digits = self.read_bytes(count.checked_mul(2).ok_or_else(|| invalid("long too large"))?)?
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect::<Vec<u16>>();
+
+        if digits.iter().any(|&d| d >= 1 << 15) {
+            return Err(invalid("bad long digit"));
+        }
+
+        if count <= 4 {
+            let magnitude = digits.iter().rev().fold(0i64, |value, &d| (value << 15) | d as i64);
+            return Ok(Value::Int(if size < 0 { -magnitude } else { magnitude }));
+        }
+
+        Ok(Value::Long(long_text(size < 0, &digits)))
+    }
+
+    fn read_seq(&mut self, len: usize) -> Result<Vec<Value>> {
+        let mut items = Vec::with_capacity(len.min(4096));
+        for _ in 0..len {
+            items.push(self.read_object()?);
+        }
+        Ok(items)
+    }
+
+    // Part of the recursion of nested code objects: the objects are read first and checked by
+    // `fill_code` once read, outside of the recursion
+    fn read_code(&mut self) -> Result<Box<Code>> {
+        let version = self.version.ok_or_else(|| invalid("code object without a python version"))?;
+        let version = (version.major, version.minor);
+
+        let mut code = self.read_code_header(version)?;
+        // `co_code` to `co_name`, or `co_qualname` from 3.11
+        let objects = self.read_seq(8)?;
+        code.firstlineno = self.read_u32()?;
+        let tables = self.read_seq(if version >= (3, 11) { 2 } else { 1 })?;
+
+        fill_code(&mut code, version, objects, tables)?;
+        Ok(code)
+    }
+
+    fn read_code_header(&mut self, version: (u8, u8)) -> Result<Box<Code>> {
+        let mut code = Box::new(Code {
+            argcount: self.read_u32()?,
+            ..Default::default()
+        });
+
+        if version >= (3, 8) {
+            code.posonlyargcount = self.read_u32()?;
+        }
+        if version >= (3, 0) {
+            code.kwonlyargcount = self.read_u32()?;
+        }
+        if version < (3, 11) {
+            code.nlocals = self.read_u32()?;
+        }
+        code.stacksize = self.read_u32()?;
+        code.flags = self.read_u32()?;
+
+        Ok(code)
+    }
+
+    fn read_collection(&mut self, type_: u8) -> Result<Value> {
+        let len = match type_ {
+            TYPE_SMALL_TUPLE => self.read_u8()? as usize,
+            _ => self.read_len()?,
+        };
+        let items = self.read_seq(len)?;
+
+        Ok(match type_ {
+            TYPE_LIST => Value::List(items),
+            TYPE_SET => Value::Set(items),
+            TYPE_FROZENSET => Value::FrozenSet(items),
+            _ => Value::Tuple(items),
+        })
+    }
+
+    fn read_slice(&mut self) -> Result<Value> {
+        let mut items = self.read_seq(3)?.into_iter();
+        Ok(Value::Slice(Box::new((items.next().unwrap(), items.next().unwrap(), items.next().unwrap()))))
+    }
+
+    fn read_dict(&mut self) -> Result<Vec<(Value, Value)>> {
+        let mut items = Vec::new();
+        loop {
+            let key = self.read_object()?;
+            if let Value::Null = key {
+                return Ok(items);
+            }
+            let value = self.read_object()?;
+            items.push((key, value));
+        }
+    }
+
+    fn read_ref(&mut self) -> Result<Value> {
+        let index = self.read_len()?;
+        // Checked before copying, the copy itself is what would not fit in memory
+        let weight = self.refs.get(index).and_then(Option::as_ref).ok_or_else(|| invalid("bad ref"))?.1;
+        self.produce(weight)?;
+        Ok(self.refs[index].as_ref().unwrap().0.clone())
+    }
+
+    // Counts a value read, and keeps it in its ref slot with the weight of everything read for it
+    fn record(&mut self, value: &Value, slot: Option<usize>, start: usize) -> Result<()> {
+        let payload = match value {
+            Value::Bytes(bytes) => bytes.len(),
+            Value::Str(text) | Value::Long(text) => text.len(),
+            _ => 0,
+        };
+        self.produce(1 + payload)?;
+
+        if let Some(slot) = slot {
+            self.refs[slot] = Some((value.clone(), self.produced - start));
+        }
+        Ok(())
+    }
+
+    fn read_scalar(&mut self, type_: u8) -> Result<Value> {
+        let value = match type_ {
+            TYPE_NULL => Value::Null,
+            TYPE_NONE => Value::None,
+            TYPE_FALSE => Value::Bool(false),
+            TYPE_TRUE => Value::Bool(true),
+            TYPE_STOPITER => Value::StopIteration,
+            TYPE_ELLIPSIS => Value::Ellipsis,
+            TYPE_INT => Value::Int(self.read_i32()? as i64),
+            TYPE_INT64 => Value::Int(i64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap())),
+            TYPE_FLOAT => Value::Float(self.read_float_text()?),
+            TYPE_BINARY_FLOAT => Value::Float(self.read_f64()?),
+            TYPE_COMPLEX => Value::Complex(self.read_float_text()?, self.read_float_text()?),
+            TYPE_BINARY_COMPLEX => Value::Complex(self.read_f64()?, self.read_f64()?),
+            TYPE_LONG => match self.read_long()? {
+                Value::Int(value) if self.is_python2() => Value::Long(value.to_string()),
+                value => value,
+            },
+            TYPE_STRING => {
+                let len = self.read_len()?;
+                Value::Bytes(self.read_bytes(len)?.to_vec())
+            }
+            // Interned strings are bytes in python 2 and get an index for `TYPE_STRINGREF`
+            TYPE_INTERNED if self.is_python2() => {
+                let len = self.read_len()?;
+                let value = Value::Bytes(self.read_bytes(len)?.to_vec());
+                self.interned.push(value.clone());
+                value
+            }
+            TYPE_STRINGREF if self.is_python2() => {
+                let index = self.read_len()?;
+                let weight = self.interned.get(index).ok_or_else(|| invalid("bad string ref"))?
+                    .as_bytes().map_or(0, <[u8]>::len);
+                self.produce(weight)?;
+                self.interned[index].clone()
+            }
+            TYPE_INTERNED | TYPE_UNICODE | TYPE_ASCII | TYPE_ASCII_INTERNED => {
+                let len = self.read_len()?;
+                Value::Str(self.read_str(len)?)
+            }
+            TYPE_SHORT_ASCII | TYPE_SHORT_ASCII_INTERNED => {
+                let len = self.read_u8()? as usize;
+                Value::Str(self.read_str(len)?)
+            }
+            _ => return Err(invalid(&format!("unsupported type code {:#04X} at {:#X}", type_, self.pos - 1))),
+        };
+        Ok(value)
+    }
+
+    /// Offset of the next object, the end of the data once everything was read
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn read_object(&mut self) -> Result<Value> {
+        if self.depth >= MAX_DEPTH {
+            return Err(invalid("data nested too deep"));
+        }
+
+        self.depth += 1;
+        let value = self.read_value();
+        self.depth -= 1;
+        value
+    }
+
+    fn read_value(&mut self) -> Result<Value> {
+        let start = self.produced;
+        let code = self.read_u8()?;
+        let flag = code & FLAG_REF != 0;
+        let type_ = code & !FLAG_REF;
+
+        // Reserve the ref slot before reading children so indexes match CPython
+        let slot = if flag {
+            self.refs.push(None);
+            Some(self.refs.len() - 1)
+        } else {
+            None
+        };
+
+        // Only containers recurse, everything else is read by other functions: debug builds give
+        // every temporary its own slot, the frames of the recursion must stay small
+        let value = match type_ {
+            TYPE_TUPLE | TYPE_SMALL_TUPLE | TYPE_LIST | TYPE_SET | TYPE_FROZENSET => self.read_collection(type_),
+            TYPE_DICT => self.read_dict().map(Value::Dict),
+            TYPE_SLICE => self.read_slice(),
+            TYPE_CODE => self.read_code().map(Value::Code),
+            TYPE_REF => return self.read_ref(),
+            _ => self.read_scalar(type_),
+        }?;
+
+        self.record(&value, slot, start)?;
+        Ok(value)
+    }
+}
+
+/// Whether `data` starts with a code object, as a pyc does after its header
+pub fn is_code(data: &[u8]) -> bool {
+    data.first().is_some_and(|&code| code & !FLAG_REF == TYPE_CODE)
+}
+
+/// Reads the first object of `data`, code objects need a version and are refused, see `loads_code`.
+/// Meant for untrusted data: nesting and the copies made by back-references are bounded, past them
+/// an error is returned
+pub fn loads(data: &[u8]) -> Result<Value> {
+    Reader::new(data).read_object()
+}
+
+/// Like `loads` for data holding code objects, such as the contents of a pyc without its header
+pub fn loads_code(data: &[u8], version: PythonVersion) -> Result<Value> {
+    Reader::with_version(data, version).read_object()
+}
+
+/// Reads a whole pyc file, the python version is the one of its header magic
+pub fn loads_pyc(data: &[u8]) -> Result<(PythonVersion, Value)> {
+    let magic: [u8; 4] = data.get(..4).ok_or_else(|| invalid("pyc shorter than its magic"))?.try_into().unwrap();
+    let version = pyc::version_for_magic(magic).ok_or_else(|| invalid(&format!("unknown pyc magic {:02X?}", magic)))?;
+
+    let code = data.get(pyc::header_size(version)..).ok_or_else(|| invalid("pyc shorter than its header"))?;
+    Ok((version, loads_code(code, version)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // marshal.dumps((1, 'abc', None, 2**70, -1.5, b'x', frozenset([2]))) on 3.12
+    const CONSTANTS: &str = "2907e901000000da036162634e6c0500000000000000000000000004e7000000000000f8bff301000000783e01000000e902000000";
+
+    // marshal.dumps(compile('x = 1\ndef f(a): return a\n', 'm.py', 'exec')) on 3.12
+    const MODULE: &str = "630000000000000000000000000100000000000000f30e000000970064005a00640184005a0179022903e90100000063010000\
+        0000000000000000000100000003000000f30600000097007c00530029014ea9002901da0161730100000020fa046d2e7079da01667206000000\
+        020000007306000000800090118828f3000000004e2902da01787206000000720300000072070000007205000000fa083c6d6f64756c653e7209\
+        00000001000000730d000000f003010101d804058001db00127207000000";
+
+    #[test]
+    fn loads_values() {
+        let value = loads(&hex(CONSTANTS)).unwrap();
+        assert_eq!(value.repr(), "(1, 'abc', None, 1180591620717411303424, -1.5, b'x', frozenset({2}))");
+        assert_eq!(value.as_seq().map(<[Value]>::len), Some(7));
+    }
+
+    #[test]
+    fn loads_code_objects() {
+        let data = hex(MODULE);
+        assert!(is_code(&data));
+        assert!(loads(&data).is_err());
+
+        let value = loads_code(&data, PythonVersion::new(3, 12)).unwrap();
+        let module = value.as_code().unwrap();
+        assert_eq!(module.name, "<module>");
+        assert_eq!(module.filename, "m.py");
+        assert_eq!(module.names, ["x", "f"]);
+
+        // The nested code object shares its filename and names with the module through refs
+        let function = module.children().next().unwrap();
+        assert_eq!((function.name.as_str(), function.filename.as_str(), function.argcount), ("f", "m.py", 1));
+        assert_eq!(function.varnames, ["a"]);
+    }
+
+    #[test]
+    fn loads_pyc_reads_the_version_from_the_header() {
+        let pyc = [hex("cb0d0d0a"), vec![0; 12], hex(MODULE)].concat();
+        let (version, module) = loads_pyc(&pyc).unwrap();
+        assert_eq!((version.major, version.minor), (3, 12));
+        assert!(module.as_code().is_some());
+
+        assert!(loads_pyc(&[0xFF, 0xFF, 0x0D, 0x0A]).is_err());
+    }
+
+    // Every tuple holds the previous one twice, 45 levels are a few hundred bytes and 2^45 values
+    #[test]
+    fn ref_bombs_are_refused() {
+        let mut data = vec![TYPE_LIST, 45, 0, 0, 0, TYPE_NONE | FLAG_REF];
+        for index in 0u32..44 {
+            data.extend([TYPE_SMALL_TUPLE | FLAG_REF, 2]);
+            for _ in 0..2 {
+                data.push(TYPE_REF);
+                data.extend(index.to_le_bytes());
+            }
+        }
+        assert!(matches!(loads(&data), Err(Error::Marshal(reason)) if reason.contains("expand")));
+    }
+
+    #[test]
+    fn string_ref_bombs_are_refused() {
+        let count = 20_000u32;
+        let mut data = vec![TYPE_LIST];
+        data.extend((count + 1).to_le_bytes());
+        data.push(TYPE_INTERNED);
+        data.extend(1000u32.to_le_bytes());
+        data.extend([b'a'; 1000]);
+        for _ in 0..count {
+            data.push(TYPE_STRINGREF);
+            data.extend(0u32.to_le_bytes());
+        }
+        let result = loads_code(&data, PythonVersion::new(2, 7));
+        assert!(matches!(result, Err(Error::Marshal(reason)) if reason.contains("expand")));
+    }
+
+    // `levels` nested 3.12 code objects, each one the only constant of the previous one
+    fn nested_code(levels: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        for level in 0..levels {
+            data.push(TYPE_CODE);
+            data.extend_from_slice(&[0; 20]);
+            data.extend_from_slice(&[TYPE_STRING, 0, 0, 0, 0, TYPE_SMALL_TUPLE, (level + 1 < levels) as u8]);
+        }
+        for _ in 0..levels {
+            data.extend_from_slice(&[TYPE_SMALL_TUPLE, 0, TYPE_SMALL_TUPLE, 0, TYPE_STRING, 0, 0, 0, 0]);
+            data.extend_from_slice(b"z\x01fz\x01fz\x01f\x01\x00\x00\x00");
+            data.extend_from_slice(&[TYPE_STRING, 0, 0, 0, 0, TYPE_STRING, 0, 0, 0, 0]);
+        }
+        data
+    }
+
+    // Nesting up to the limit is read, written and dropped on a thread with the default stack size,
+    // the one of the worker threads, in debug builds too
+    #[test]
+    fn nesting_up_to_the_limit_fits_in_a_default_stack() {
+        std::thread::spawn(|| {
+            let mut data = [TYPE_SMALL_TUPLE, 1].repeat(MAX_DEPTH - 1);
+            data.push(TYPE_NONE);
+            let value = loads(&data).unwrap();
+            assert_eq!(value.repr(), format!("{}None{}", "(".repeat(MAX_DEPTH - 1), ",)".repeat(MAX_DEPTH - 1)));
+
+            let mut data = [TYPE_LIST, 1, 0, 0, 0].repeat(MAX_DEPTH - 1);
+            data.push(TYPE_NONE);
+            assert_eq!(loads(&data).unwrap().repr(), format!("{}None{}", "[".repeat(MAX_DEPTH - 1), "]".repeat(MAX_DEPTH - 1)));
+
+            let data = nested_code(MAX_DEPTH / 2);
+            let version = PythonVersion { major: 3, minor: 12 };
+            assert!(loads_code(&data, version).is_ok());
+            assert!(crate::disasm::disassemble(&data, version).is_ok());
+        }).join().unwrap();
+    }
+
+    #[test]
+    fn deep_nesting_is_refused() {
+        std::thread::spawn(|| {
+            let mut data = [TYPE_SMALL_TUPLE, 1].repeat(MAX_DEPTH);
+            data.push(TYPE_NONE);
+            assert!(matches!(loads(&data), Err(Error::Marshal(reason)) if reason.contains("nested")));
+
+            let mut data = [TYPE_SMALL_TUPLE, 1].repeat(100_000);
+            data.push(TYPE_NONE);
+            assert!(matches!(loads(&data), Err(Error::Marshal(reason)) if reason.contains("nested")));
+
+            let data = nested_code(MAX_DEPTH / 2 + 1);
+            let version = PythonVersion { major: 3, minor: 12 };
+            assert!(matches!(loads_code(&data, version), Err(Error::Marshal(reason)) if reason.contains("nested")));
+        }).join().unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+// Opcode names of every supported CPython version, from the `opcode` module of each release.
+// 3.7 to 3.10 are described as changes on top of 3.6, 3.11 and later renumbered everything.
+// Specialized and instrumented opcodes are left out, they never appear in marshalled code
+
+use crate::pyc::PythonVersion;
+
+type Table = &'static [(u8, &'static str)];
+
+const PY27: Table = &[
+    (0, "STOP_CODE"), (1, "POP_TOP"), (2, "ROT_TWO"), (3, "ROT_THREE"), (4, "DUP_TOP"), (5, "ROT_FOUR"),
+    (9, "NOP"), (10, "UNARY_POSITIVE"), (11, "UNARY_NEGATIVE"), (12, "UNARY_NOT"), (13, "UNARY_CONVERT"),
+    (15, "UNARY_INVERT"), (19, "BINARY_POWER"), (20, "BINARY_MULTIPLY"), (21, "BINARY_DIVIDE"),
+    (22, "BINARY_MODULO"), (23, "BINARY_ADD"), (24, "BINARY_SUBTRACT"), (25, "BINARY_SUBSCR"),
+    (26, "BINARY_FLOOR_DIVIDE"), (27, "BINARY_TRUE_DIVIDE"), (28, "INPLACE_FLOOR_DIVIDE"),
+    (29, "INPLACE_TRUE_DIVIDE"), (30, "SLICE+0"), (31, "SLICE+1"), (32, "SLICE+2"), (33, "SLICE+3"),
+    (40, "STORE_SLICE+0"), (41, "STORE_SLICE+1"), (42, "STORE_SLICE+2"), (43, "STORE_SLICE+3"),
+    (50, "DELETE_SLICE+0"), (51, "DELETE_SLICE+1"), (52, "DELETE_SLICE+2"), (53, "DELETE_SLICE+3"),
+    (54, "STORE_MAP"), (55, "INPLACE_ADD"), (56, "INPLACE_SUBTRACT"), (57, "INPLACE_MULTIPLY"),
+    (58, "INPLACE_DIVIDE"), (59, "INPLACE_MODULO"), (60, "STORE_SUBSCR"), (61, "DELETE_SUBSCR"),
+    (62, "BINARY_LSHIFT"), (63, "BINARY_RSHIFT"), (64, "BINARY_AND"), (65, "BINARY_XOR"), (66, "BINARY_OR"),
+    (67, "INPLACE_POWER"), (68, "GET_ITER"), (70, "PRINT_EXPR"), (71, "PRINT_ITEM"), (72, "PRINT_NEWLINE"),
+    (73, "PRINT_ITEM_TO"), (74, "PRINT_NEWLINE_TO"), (75, "INPLACE_LSHIFT"), (76, "INPLACE_RSHIFT"),
+    (77, "INPLACE_AND"), (78, "INPLACE_XOR"), (79, "INPLACE_OR"), (80, "BREAK_LOOP"), (81, "WITH_CLEANUP"),
+    (82, "LOAD_LOCALS"), (83, "RETURN_VALUE"), (84, "IMPORT_STAR"), (85, "EXEC_STMT"), (86, "YIELD_VALUE"),
+    (87, "POP_BLOCK"), (88, "END_FINALLY"), (89, "BUILD_CLASS"), (90, "STORE_NAME"), (91, "DELETE_NAME"),
+    (92, "UNPACK_SEQUENCE"), (93, "FOR_ITER"), (94, "LIST_APPEND"), (95, "STORE_ATTR"), (96, "DELETE_ATTR"),
+    (97, "STORE_GLOBAL"), (98, "DELETE_GLOBAL"), (99, "DUP_TOPX"), (100, "LOAD_CONST"), (101, "LOAD_NAME"),
+    (102, "BUILD_TUPLE"), (103, "BUILD_LIST"), (104, "BUILD_SET"), (105, "BUILD_MAP"), (106, "LOAD_ATTR"),
+    (107, "COMPARE_OP"), (108, "IMPORT_NAME"), (109, "IMPORT_FROM"), (110, "JUMP_FORWARD"),
+    (111, "JUMP_IF_FALSE_OR_POP"), (112, "JUMP_IF_TRUE_OR_POP"), (113, "JUMP_ABSOLUTE"),
+    (114, "POP_JUMP_IF_FALSE"), (115, "POP_JUMP_IF_TRUE"), (116, "LOAD_GLOBAL"), (119, "CONTINUE_LOOP"),
+    (120, "SETUP_LOOP"), (121, "SETUP_EXCEPT"), (122, "SETUP_FINALLY"), (124, "LOAD_FAST"), (125, "STORE_FAST"),
+    (126, "DELETE_FAST"), (130, "RAISE_VARARGS"), (131, "CALL_FUNCTION"), (132, "MAKE_FUNCTION"),
+    (133, "BUILD_SLICE"), (134, "MAKE_CLOSURE"), (135, "LOAD_CLOSURE"), (136, "LOAD_DEREF"),
+    (137, "STORE_DEREF"), (140, "CALL_FUNCTION_VAR"), (141, "CALL_FUNCTION_KW"), (142, "CALL_FUNCTION_VAR_KW"),
+    (143, "SETUP_WITH"), (145, "EXTENDED_ARG"), (146, "SET_ADD"), (147, "MAP_ADD"),
+];
+
+const PY36: Table = &[
+    (1, "POP_TOP"), (2, "ROT_TWO"), (3, "ROT_THREE"), (4, "DUP_TOP"), (5, "DUP_TOP_TWO"), (9, "NOP"),
+    (10, "UNARY_POSITIVE"), (11, "UNARY_NEGATIVE"), (12, "UNARY_NOT"), (15, "UNARY_INVERT"),
+    (16, "BINARY_MATRIX_MULTIPLY"), (17, "INPLACE_MATRIX_MULTIPLY"), (19, "BINARY_POWER"),
+    (20, "BINARY_MULTIPLY"), (22, "BINARY_MODULO"), (23, "BINARY_ADD"), (24, "BINARY_SUBTRACT"),
+    (25, "BINARY_SUBSCR"), (26, "BINARY_FLOOR_DIVIDE"), (27, "BINARY_TRUE_DIVIDE"),
+    (28, "INPLACE_FLOOR_DIVIDE"), (29, "INPLACE_TRUE_DIVIDE"), (50, "GET_AITER"), (51, "GET_ANEXT"),
+    (52, "BEFORE_ASYNC_WITH"), (55, "INPLACE_ADD"), (56, "INPLACE_SUBTRACT"), (57, "INPLACE_MULTIPLY"),
+    (59, "INPLACE_MODULO"), (60, "STORE_SUBSCR"), (61, "DELETE_SUBSCR"), (62, "BINARY_LSHIFT"),
+    (63, "BINARY_RSHIFT"), (64, "BINARY_AND"), (65, "BINARY_XOR"), (66, "BINARY_OR"), (67, "INPLACE_POWER"),
+    (68, "GET_ITER"), (69, "GET_YIELD_FROM_ITER"), (70, "PRINT_EXPR"), (71, "LOAD_BUILD_CLASS"),
+    (72, "YIELD_FROM"), (73, "GET_AWAITABLE"), (75, "INPLACE_LSHIFT"), (76, "INPLACE_RSHIFT"),
+    (77, "INPLACE_AND"), (78, "INPLACE_XOR"), (79, "INPLACE_OR"), (80, "BREAK_LOOP"),
+    (81, "WITH_CLEANUP_START"), (82, "WITH_CLEANUP_FINISH"), (83, "RETURN_VALUE"), (84, "IMPORT_STAR"),
+    (85, "SETUP_ANNOTATIONS"), (86, "YIELD_VALUE"), (87, "POP_BLOCK"), (88, "END_FINALLY"), (89, "POP_EXCEPT"),
+    (90, "STORE_NAME"), (91, "DELETE_NAME"), (92, "UNPACK_SEQUENCE"), (93, "FOR_ITER"), (94, "UNPACK_EX"),
+    (95, "STORE_ATTR"), (96, "DELETE_ATTR"), (97, "STORE_GLOBAL"), (98, "DELETE_GLOBAL"), (100, "LOAD_CONST"),
+    (101, "LOAD_NAME"), (102, "BUILD_TUPLE"), (103, "BUILD_LIST"), (104, "BUILD_SET"), (105, "BUILD_MAP"),
+    (106, "LOAD_ATTR"), (107, "COMPARE_OP"), (108, "IMPORT_NAME"), (109, "IMPORT_FROM"),
+    (110, "JUMP_FORWARD"), (111, "JUMP_IF_FALSE_OR_POP"), (112, "JUMP_IF_TRUE_OR_POP"),
+    (113, "JUMP_ABSOLUTE"), (114, "POP_JUMP_IF_FALSE"), (115, "POP_JUMP_IF_TRUE"), (116, "LOAD_GLOBAL"),
+    (119, "CONTINUE_LOOP"), (120, "SETUP_LOOP"), (121, "SETUP_EXCEPT"), (122, "SETUP_FINALLY"),
+    (124, "LOAD_FAST"), (125, "STORE_FAST"), (126, "DELETE_FAST"), (127, "STORE_ANNOTATION"),
+    (130, "RAISE_VARARGS"), (131, "CALL_FUNCTION"), (132, "MAKE_FUNCTION"), (133, "BUILD_SLICE"),
+    (135, "LOAD_CLOSURE"), (136, "LOAD_DEREF"), (137, "STORE_DEREF"), (138, "DELETE_DEREF"),
+    (141, "CALL_FUNCTION_KW"), (142, "CALL_FUNCTION_EX"), (143, "SETUP_WITH"), (144, "EXTENDED_ARG"),
+    (145, "LIST_APPEND"), (146, "SET_ADD"), (147, "MAP_ADD"), (148, "LOAD_CLASSDEREF"),
+    (149, "BUILD_LIST_UNPACK"), (150, "BUILD_MAP_UNPACK"), (151, "BUILD_MAP_UNPACK_WITH_CALL"),
+    (152, "BUILD_TUPLE_UNPACK"), (153, "BUILD_SET_UNPACK"), (154, "SETUP_ASYNC_WITH"), (155, "FORMAT_VALUE"),
+    (156, "BUILD_CONST_KEY_MAP"), (157, "BUILD_STRING"), (158, "BUILD_TUPLE_UNPACK_WITH_CALL"),
+];
+
+// Changes of each version on top of the previous one, `None` removes the opcode
+type Changes = &'static [(u8, Option<&'static str>)];
+
+const PY37: Changes = &[(127, None), (160, Some("LOAD_METHOD")), (161, Some("CALL_METHOD"))];
+
+const PY38: Changes = &[
+    (6, Some("ROT_FOUR")), (53, Some("BEGIN_FINALLY")), (54, Some("END_ASYNC_FOR")), (80, None), (119, None),
+    (120, None), (121, None), (162, Some("CALL_FINALLY")), (163, Some("POP_FINALLY")),
+];
+
+const PY39: Changes = &[
+    (48, Some("RERAISE")), (49, Some("WITH_EXCEPT_START")), (53, None), (74, Some("LOAD_ASSERTION_ERROR")),
+    (81, None), (82, Some("LIST_TO_TUPLE")), (88, None), (117, Some("IS_OP")), (118, Some("CONTAINS_OP")),
+    (121, Some("JUMP_IF_NOT_EXC_MATCH")), (149, None), (150, None), (151, None), (152, None), (153, None),
+    (158, None), (162, Some("LIST_EXTEND")), (163, Some("SET_UPDATE")), (164, Some("DICT_MERGE")),
+    (165, Some("DICT_UPDATE")),
+];
+
+const PY310: Changes = &[
+    (30, Some("GET_LEN")), (31, Some("MATCH_MAPPING")), (32, Some("MATCH_SEQUENCE")), (33, Some("MATCH_KEYS")),
+    (34, Some("COPY_DICT_WITHOUT_KEYS")), (48, None), (99, Some("ROT_N")), (119, Some("RERAISE")),
+    (129, Some("GEN_START")), (152, Some("MATCH_CLASS")),
+];
+
+const PY311: Table = &[
+    (0, "CACHE"), (1, "POP_TOP"), (2, "PUSH_NULL"), (9, "NOP"), (10, "UNARY_POSITIVE"), (11, "UNARY_NEGATIVE"),
+    (12, "UNARY_NOT"), (15, "UNARY_INVERT"), (25, "BINARY_SUBSCR"), (30, "GET_LEN"), (31, "MATCH_MAPPING"),
+    (32, "MATCH_SEQUENCE"), (33, "MATCH_KEYS"), (35, "PUSH_EXC_INFO"), (36, "CHECK_EXC_MATCH"),
+    (37, "CHECK_EG_MATCH"), (49, "WITH_EXCEPT_START"), (50, "GET_AITER"), (51, "GET_ANEXT"),
+    (52, "BEFORE_ASYNC_WITH"), (53, "BEFORE_WITH"), (54, "END_ASYNC_FOR"), (60, "STORE_SUBSCR"),
+    (61, "DELETE_SUBSCR"), (68, "GET_ITER"), (69, "GET_YIELD_FROM_ITER"), (70, "PRINT_EXPR"),
+    (71, "LOAD_BUILD_CLASS"), (74, "LOAD_ASSERTION_ERROR"), (75, "RETURN_GENERATOR"), (82, "LIST_TO_TUPLE"),
+    (83, "RETURN_VALUE"), (84, "IMPORT_STAR"), (85, "SETUP_ANNOTATIONS"), (86, "YIELD_VALUE"),
+    (87, "ASYNC_GEN_WRAP"), (88, "PREP_RERAISE_STAR"), (89, "POP_EXCEPT"), (90, "STORE_NAME"),
+    (91, "DELETE_NAME"), (92, "UNPACK_SEQUENCE"), (93, "FOR_ITER"), (94, "UNPACK_EX"), (95, "STORE_ATTR"),
+    (96, "DELETE_ATTR"), (97, "STORE_GLOBAL"), (98, "DELETE_GLOBAL"), (99, "SWAP"), (100, "LOAD_CONST"),
+    (101, "LOAD_NAME"), (102, "BUILD_TUPLE"), (103, "BUILD_LIST"), (104, "BUILD_SET"), (105, "BUILD_MAP"),
+    (106, "LOAD_ATTR"), (107, "COMPARE_OP"), (108, "IMPORT_NAME"), (109, "IMPORT_FROM"),
+    (110, "JUMP_FORWARD"), (111, "JUMP_IF_FALSE_OR_POP"), (112, "JUMP_IF_TRUE_OR_POP"),
+    (114, "POP_JUMP_FORWARD_IF_FALSE"), (115, "POP_JUMP_FORWARD_IF_TRUE"), (116, "LOAD_GLOBAL"),
+    (117, "IS_OP"), (118, "CONTAINS_OP"), (119, "RERAISE"), (120, "COPY"), (122, "BINARY_OP"), (123, "SEND"),
+    (124, "LOAD_FAST"), (125, "STORE_FAST"), (126, "DELETE_FAST"), (128, "POP_JUMP_FORWARD_IF_NOT_NONE"),
+    (129, "POP_JUMP_FORWARD_IF_NONE"), (130, "RAISE_VARARGS"), (131, "GET_AWAITABLE"), (132, "MAKE_FUNCTION"),
+    (133, "BUILD_SLICE"), (134, "JUMP_BACKWARD_NO_INTERRUPT"), (135, "MAKE_CELL"), (136, "LOAD_CLOSURE"),
+    (137, "LOAD_DEREF"), (138, "STORE_DEREF"), (139, "DELETE_DEREF"), (140, "JUMP_BACKWARD"),
+    (142, "CALL_FUNCTION_EX"), (144, "EXTENDED_ARG"), (145, "LIST_APPEND"), (146, "SET_ADD"), (147, "MAP_ADD"),
+    (148, "LOAD_CLASSDEREF"), (149, "COPY_FREE_VARS"), (151, "RESUME"), (152, "MATCH_CLASS"),
+    (155, "FORMAT_VALUE"), (156, "BUILD_CONST_KEY_MAP"), (157, "BUILD_STRING"), (160, "LOAD_METHOD"),
+    (162, "LIST_EXTEND"), (163, "SET_UPDATE"), (164, "DICT_MERGE"), (165, "DICT_UPDATE"), (166, "PRECALL"),
+    (171, "CALL"), (172, "KW_NAMES"), (173, "POP_JUMP_BACKWARD_IF_NOT_NONE"),
+    (174, "POP_JUMP_BACKWARD_IF_NONE"), (175, "POP_JUMP_BACKWARD_IF_FALSE"), (176, "POP_JUMP_BACKWARD_IF_TRUE"),
+];
+
+const PY312: Table = &[
+    (0, "CACHE"), (1, "POP_TOP"), (2, "PUSH_NULL"), (3, "INTERPRETER_EXIT"), (4, "END_FOR"), (5, "END_SEND"),
+    (9, "NOP"), (11, "UNARY_NEGATIVE"), (12, "UNARY_NOT"), (15, "UNARY_INVERT"), (17, "RESERVED"),
+    (25, "BINARY_SUBSCR"), (26, "BINARY_SLICE"), (27, "STORE_SLICE"), (30, "GET_LEN"), (31, "MATCH_MAPPING"),
+    (32, "MATCH_SEQUENCE"), (33, "MATCH_KEYS"), (35, "PUSH_EXC_INFO"), (36, "CHECK_EXC_MATCH"),
+    (37, "CHECK_EG_MATCH"), (49, "WITH_EXCEPT_START"), (50, "GET_AITER"), (51, "GET_ANEXT"),
+    (52, "BEFORE_ASYNC_WITH"), (53, "BEFORE_WITH"), (54, "END_ASYNC_FOR"), (55, "CLEANUP_THROW"),
+    (60, "STORE_SUBSCR"), (61, "DELETE_SUBSCR"), (68, "GET_ITER"), (69, "GET_YIELD_FROM_ITER"),
+    (71, "LOAD_BUILD_CLASS"), (74, "LOAD_ASSERTION_ERROR"), (75, "RETURN_GENERATOR"), (83, "RETURN_VALUE"),
+    (85, "SETUP_ANNOTATIONS"), (87, "LOAD_LOCALS"), (89, "POP_EXCEPT"), (90, "STORE_NAME"),
+    (91, "DELETE_NAME"), (92, "UNPACK_SEQUENCE"), (93, "FOR_ITER"), (94, "UNPACK_EX"), (95, "STORE_ATTR"),
+    (96, "DELETE_ATTR"), (97, "STORE_GLOBAL"), (98, "DELETE_GLOBAL"), (99, "SWAP"), (100, "LOAD_CONST"),
+    (101, "LOAD_NAME"), (102, "BUILD_TUPLE"), (103, "BUILD_LIST"), (104, "BUILD_SET"), (105, "BUILD_MAP"),
+    (106, "LOAD_ATTR"), (107, "COMPARE_OP"), (108, "IMPORT_NAME"), (109, "IMPORT_FROM"),
+    (110, "JUMP_FORWARD"), (114, "POP_JUMP_IF_FALSE"), (115, "POP_JUMP_IF_TRUE"), (116, "LOAD_GLOBAL"),
+    (117, "IS_OP"), (118, "CONTAINS_OP"), (119, "RERAISE"), (120, "COPY"), (121, "RETURN_CONST"),
+    (122, "BINARY_OP"), (123, "SEND"), (124, "LOAD_FAST"), (125, "STORE_FAST"), (126, "DELETE_FAST"),
+    (127, "LOAD_FAST_CHECK"), (128, "POP_JUMP_IF_NOT_NONE"), (129, "POP_JUMP_IF_NONE"),
+    (130, "RAISE_VARARGS"), (131, "GET_AWAITABLE"), (132, "MAKE_FUNCTION"), (133, "BUILD_SLICE"),
+    (134, "JUMP_BACKWARD_NO_INTERRUPT"), (135, "MAKE_CELL"), (136, "LOAD_CLOSURE"), (137, "LOAD_DEREF"),
+    (138, "STORE_DEREF"), (139, "DELETE_DEREF"), (140, "JUMP_BACKWARD"), (141, "LOAD_SUPER_ATTR"),
+    (142, "CALL_FUNCTION_EX"), (143, "LOAD_FAST_AND_CLEAR"), (144, "EXTENDED_ARG"), (145, "LIST_APPEND"),
+    (146, "SET_ADD"), (147, "MAP_ADD"), (149, "COPY_FREE_VARS"), (150, "YIELD_VALUE"), (151, "RESUME"),
+    (152, "MATCH_CLASS"), (155, "FORMAT_VALUE"), (156, "BUILD_CONST_KEY_MAP"), (157, "BUILD_STRING"),
+    (162, "LIST_EXTEND"), (163, "SET_UPDATE"), (164, "DICT_MERGE"), (165, "DICT_UPDATE"), (171, "CALL"),
+    (172, "KW_NAMES"), (173, "CALL_INTRINSIC_1"), (174, "CALL_INTRINSIC_2"),
+    (175, "LOAD_FROM_DICT_OR_GLOBALS"), (176, "LOAD_FROM_DICT_OR_DEREF"),
+];
+
+const PY313: Table = &[
+    (0, "CACHE"), (1, "BEFORE_ASYNC_WITH"), (2, "BEFORE_WITH"), (4, "BINARY_SLICE"), (5, "BINARY_SUBSCR"),
+    (6, "CHECK_EG_MATCH"), (7, "CHECK_EXC_MATCH"), (8, "CLEANUP_THROW"), (9, "DELETE_SUBSCR"),
+    (10, "END_ASYNC_FOR"), (11, "END_FOR"), (12, "END_SEND"), (13, "EXIT_INIT_CHECK"), (14, "FORMAT_SIMPLE"),
+    (15, "FORMAT_WITH_SPEC"), (16, "GET_AITER"), (17, "RESERVED"), (18, "GET_ANEXT"), (19, "GET_ITER"),
+    (20, "GET_LEN"), (21, "GET_YIELD_FROM_ITER"), (22, "INTERPRETER_EXIT"), (23, "LOAD_ASSERTION_ERROR"),
+    (24, "LOAD_BUILD_CLASS"), (25, "LOAD_LOCALS"), (26, "MAKE_FUNCTION"), (27, "MATCH_KEYS"),
+    (28, "MATCH_MAPPING"), (29, "MATCH_SEQUENCE"), (30, "NOP"), (31, "POP_EXCEPT"), (32, "POP_TOP"),
+    (33, "PUSH_EXC_INFO"), (34, "PUSH_NULL"), (35, "RETURN_GENERATOR"), (36, "RETURN_VALUE"),
+    (37, "SETUP_ANNOTATIONS"), (38, "STORE_SLICE"), (39, "STORE_SUBSCR"), (40, "TO_BOOL"), (41, "UNARY_INVERT"),
+    (42, "UNARY_NEGATIVE"), (43, "UNARY_NOT"), (44, "WITH_EXCEPT_START"), (45, "BINARY_OP"),
+    (46, "BUILD_CONST_KEY_MAP"), (47, "BUILD_LIST"), (48, "BUILD_MAP"), (49, "BUILD_SET"), (50, "BUILD_SLICE"),
+    (51, "BUILD_STRING"), (52, "BUILD_TUPLE"), (53, "CALL"), (54, "CALL_FUNCTION_EX"), (55, "CALL_INTRINSIC_1"),
+    (56, "CALL_INTRINSIC_2"), (57, "CALL_KW"), (58, "COMPARE_OP"), (59, "CONTAINS_OP"), (60, "CONVERT_VALUE"),
+    (61, "COPY"), (62, "COPY_FREE_VARS"), (63, "DELETE_ATTR"), (64, "DELETE_DEREF"), (65, "DELETE_FAST"),
+    (66, "DELETE_GLOBAL"), (67, "DELETE_NAME"), (68, "DICT_MERGE"), (69, "DICT_UPDATE"), (70, "ENTER_EXECUTOR"),
+    (71, "EXTENDED_ARG"), (72, "FOR_ITER"), (73, "GET_AWAITABLE"), (74, "IMPORT_FROM"), (75, "IMPORT_NAME"),
+    (76, "IS_OP"), (77, "JUMP_BACKWARD"), (78, "JUMP_BACKWARD_NO_INTERRUPT"), (79, "JUMP_FORWARD"),
+    (80, "LIST_APPEND"), (81, "LIST_EXTEND"), (82, "LOAD_ATTR"), (83, "LOAD_CONST"), (84, "LOAD_DEREF"),
+    (85, "LOAD_FAST"), (86, "LOAD_FAST_AND_CLEAR"), (87, "LOAD_FAST_CHECK"), (88, "LOAD_FAST_LOAD_FAST"),
+    (89, "LOAD_FROM_DICT_OR_DEREF"), (90, "LOAD_FROM_DICT_OR_GLOBALS"), (91, "LOAD_GLOBAL"), (92, "LOAD_NAME"),
+    (93, "LOAD_SUPER_ATTR"), (94, "MAKE_CELL"), (95, "MAP_ADD"), (96, "MATCH_CLASS"), (97, "POP_JUMP_IF_FALSE"),
+    (98, "POP_JUMP_IF_NONE"), (99, "POP_JUMP_IF_NOT_NONE"), (100, "POP_JUMP_IF_TRUE"), (101, "RAISE_VARARGS"),
+    (102, "RERAISE"), (103, "RETURN_CONST"), (104, "SEND"), (105, "SET_ADD"), (106, "SET_FUNCTION_ATTRIBUTE"),
+    (107, "SET_UPDATE"), (108, "STORE_ATTR"), (109, "STORE_DEREF"), (110, "STORE_FAST"),
+    (111, "STORE_FAST_LOAD_FAST"), (112, "STORE_FAST_STORE_FAST"), (113, "STORE_GLOBAL"), (114, "STORE_NAME"),
+    (115, "SWAP"), (116, "UNPACK_EX"), (117, "UNPACK_SEQUENCE"), (118, "YIELD_VALUE"), (149, "RESUME"),
+];
+
+/// Opcode names of one python version, indexed by opcode
+pub struct Opcodes {
+    pub version: PythonVersion,
+    names: [Option<&'static str>; 256],
+    /// Opcodes from this one on take an argument
+    have_argument: u8
+}
+
+impl Opcodes {
+    /// Table for 2.7 or 3.6 to 3.13
+    pub fn for_version(version: PythonVersion) -> Option<Self> {
+        let minor = version.minor;
+        let (base, changes, have_argument): (Table, &[Changes], u8) = match (version.major, minor) {
+            (2, 7) => (PY27, &[], 90),
+            (3, 6..=10) => (PY36, &[PY37, PY38, PY39, PY310][..minor as usize - 6], 90),
+            (3, 11) => (PY311, &[], 90),
+            (3, 12) => (PY312, &[], 90),
+            (3, 13) => (PY313, &[], 45),
+            _ => return None,
+        };
+
+        let mut names = [None; 256];
+        for &(op, name) in base {
+            names[op as usize] = Some(name);
+        }
+        for &(op, name) in changes.iter().flat_map(|changes| changes.iter()) {
+            names[op as usize] = name;
+        }
+
+        Some(Opcodes { version, names, have_argument })
+    }
+
+    pub fn name(&self, op: u8) -> Option<&'static str> {
+        self.names[op as usize]
+    }
+
+    pub fn has_argument(&self, op: u8) -> bool {
+        op >= self.have_argument
+    }
+
+    /// Before 3.6 instructions without argument take one byte and the others three
+    pub fn is_wordcode(&self) -> bool {
+        self.version >= PythonVersion::new(3, 6)
+    }
+
+    /// Bytes the argument of `EXTENDED_ARG` is shifted by
+    pub fn extended_arg_shift(&self) -> u32 {
+        if self.is_wordcode() { 8 } else { 16 }
+    }
+}
+
+/// `COMPARE_OP` operators, `exception match` and `BAD` were dropped in 3.9
+pub const CMP_OP: [&str; 12] = ["<", "<=", "==", "!=", ">", ">=", "in", "not in", "is", "is not", "exception match", "BAD"];
+
+/// `BINARY_OP` operators of 3.11+
+pub const NB_OPS: [&str; 26] = [
+    "+", "&", "//", "<<", "@", "*", "%", "|", "**", ">>", "-", "/", "^",
+    "+=", "&=", "//=", "<<=", "@=", "*=", "%=", "|=", "**=", ">>=", "-=", "/=", "^=",
+];
+
+/// `CALL_INTRINSIC_1` functions of 3.12+
+pub const INTRINSIC_1: [&str; 12] = [
+    "INTRINSIC_1_INVALID", "INTRINSIC_PRINT", "INTRINSIC_IMPORT_STAR", "INTRINSIC_STOPITERATION_ERROR",
+    "INTRINSIC_ASYNC_GEN_WRAP", "INTRINSIC_UNARY_POSITIVE", "INTRINSIC_LIST_TO_TUPLE", "INTRINSIC_TYPEVAR",
+    "INTRINSIC_PARAMSPEC", "INTRINSIC_TYPEVARTUPLE", "INTRINSIC_SUBSCRIPT_GENERIC", "INTRINSIC_TYPEALIAS",
+];
+
+/// `CALL_INTRINSIC_2` functions of 3.12+
+pub const INTRINSIC_2: [&str; 6] = [
+    "INTRINSIC_2_INVALID", "INTRINSIC_PREP_RERAISE_STAR", "INTRINSIC_TYPEVAR_WITH_BOUND",
+    "INTRINSIC_TYPEVAR_WITH_CONSTRAINTS", "INTRINSIC_SET_FUNCTION_TYPE_PARAMS", "INTRINSIC_SET_TYPEPARAM_DEFAULT",
+];
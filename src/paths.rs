@@ -0,0 +1,165 @@
+// Archive names come from untrusted samples, they are checked before being joined on the output directory
+
+use std::path::{Component, Path, PathBuf};
+
+fn is_drive(component: &str) -> bool {
+    let bytes = component.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+// A name joined on a path on Windows leaves it when it holds a drive (`C:`, even after other components)
+// and `:` also names NTFS streams, so it is refused in every component
+fn check_name(component: &str) -> Result<(), &'static str> {
+    if is_drive(component) {
+        return Err("drive letter");
+    }
+    if component.contains(':') {
+        return Err("`:` in a name");
+    }
+    Ok(())
+}
+
+// Last check on the joined path, the platform must see nothing but names in it
+fn only_names(path: &Path) -> bool {
+    path.components().all(|component| matches!(component, Component::Normal(_)))
+}
+
+// Relative path for an entry name, `\` separators are accepted and `.` or empty components dropped.
+// Absolute paths, `..` components and `:` in any component (drive letters, streams) are refused with the reason.
+pub fn sanitize(name: &str) -> Result<PathBuf, &'static str> {
+    let normalized = name.replace('\\', "/");
+
+    if normalized.starts_with('/') {
+        return Err("absolute path");
+    }
+
+    let mut path = PathBuf::new();
+
+    for component in normalized.split('/') {
+        match component {
+            "" | "." => continue,
+            ".." => return Err("parent directory component"),
+            _ => {
+                check_name(component)?;
+                path.push(component);
+            }
+        }
+    }
+
+    if path.as_os_str().is_empty() {
+        return Err("empty name");
+    }
+    if !only_names(&path) {
+        return Err("not a relative path");
+    }
+
+    Ok(path)
+}
+
+// Where the symlink `name` pointing to `target` resolves, relative to the output directory. `..` may
+// only climb back up to the output directory, absolute targets and drive letters are refused.
+// `..` is only accepted before the first name: after a name that is itself a link it would climb from
+// the link target, not from where the text says, so `a/..` is refused.
+pub fn resolve_link(name: &str, target: &str) -> Result<PathBuf, &'static str> {
+    let link = sanitize(name)?;
+    let normalized = target.replace('\\', "/");
+
+    if normalized.starts_with('/') {
+        return Err("absolute link target");
+    }
+
+    let mut path = link.parent().map(PathBuf::from).unwrap_or_default();
+    let mut named = false;
+
+    for component in normalized.split('/') {
+        match component {
+            "" | "." => continue,
+            ".." if named => return Err("`..` after a name in a link target"),
+            ".." => if !path.pop() {
+                return Err("link target outside of the output directory");
+            },
+            _ => {
+                check_name(component)?;
+                path.push(component);
+                named = true;
+            }
+        }
+    }
+
+    if !only_names(&path) {
+        return Err("not a relative path");
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_keeps_relative_names() {
+        assert_eq!(sanitize("a/b.pyc"), Ok(PathBuf::from("a").join("b.pyc")));
+        assert_eq!(sanitize("a\\b\\c.pyd"), Ok(PathBuf::from("a").join("b").join("c.pyd")));
+        assert_eq!(sanitize("./a//b/./c"), Ok(PathBuf::from("a").join("b").join("c")));
+    }
+
+    #[test]
+    fn sanitize_refuses_parent_components() {
+        assert_eq!(sanitize("../x"), Err("parent directory component"));
+        assert_eq!(sanitize("a/../../x"), Err("parent directory component"));
+        assert_eq!(sanitize("a\\..\\..\\x"), Err("parent directory component"));
+        assert_eq!(sanitize("..\\x"), Err("parent directory component"));
+    }
+
+    #[test]
+    fn sanitize_refuses_absolute_paths() {
+        assert_eq!(sanitize("/etc/passwd"), Err("absolute path"));
+        assert_eq!(sanitize("\\Windows\\x"), Err("absolute path"));
+        assert_eq!(sanitize("\\\\server\\share\\x"), Err("absolute path"));
+    }
+
+    #[test]
+    fn sanitize_refuses_drives_in_any_component() {
+        assert_eq!(sanitize("C:/Windows/x"), Err("drive letter"));
+        assert_eq!(sanitize("C:x"), Err("drive letter"));
+        assert_eq!(sanitize("./C:/Windows/x"), Err("drive letter"));
+        assert_eq!(sanitize(".\\C:\\Windows\\x"), Err("drive letter"));
+        assert_eq!(sanitize("a/C:/x"), Err("drive letter"));
+        assert_eq!(sanitize("a/b.txt:stream"), Err("`:` in a name"));
+    }
+
+    #[test]
+    fn sanitize_refuses_empty_names() {
+        assert_eq!(sanitize(""), Err("empty name"));
+        assert_eq!(sanitize("."), Err("empty name"));
+        assert_eq!(sanitize(".//./"), Err("empty name"));
+    }
+
+    #[test]
+    fn resolve_link_stays_in_the_output_directory() {
+        assert_eq!(resolve_link("lib/libz.so", "libz.so.1"), Ok(PathBuf::from("lib").join("libz.so.1")));
+        assert_eq!(resolve_link("a/b/link", "../../c"), Ok(PathBuf::from("c")));
+        assert_eq!(resolve_link("link", "a\\b"), Ok(PathBuf::from("a").join("b")));
+        assert_eq!(resolve_link("link", "../x"), Err("link target outside of the output directory"));
+        assert_eq!(resolve_link("a/link", "..\\..\\x"), Err("link target outside of the output directory"));
+        assert_eq!(resolve_link("link", "/etc/passwd"), Err("absolute link target"));
+    }
+
+    #[test]
+    fn resolve_link_refuses_drives_in_any_component() {
+        assert_eq!(resolve_link("link", "C:/Windows"), Err("drive letter"));
+        assert_eq!(resolve_link("link", "./C:/Windows"), Err("drive letter"));
+        assert_eq!(resolve_link("link", "a/C:/x"), Err("drive letter"));
+        assert_eq!(resolve_link("./C:/link", "x"), Err("drive letter"));
+    }
+
+    #[test]
+    fn resolve_link_refuses_parent_after_a_name() {
+        assert_eq!(resolve_link("a/b/link", "../c/d"), Ok(PathBuf::from("a").join("c").join("d")));
+        assert_eq!(resolve_link("link", "a/up/../.."), Err("`..` after a name in a link target"));
+        assert_eq!(resolve_link("a/link", "../b/../../x"), Err("`..` after a name in a link target"));
+        assert_eq!(resolve_link("link", "a\\..\\b"), Err("`..` after a name in a link target"));
+        assert_eq!(resolve_link("a/b/link", "./.././../c"), Ok(PathBuf::from("c")));
+    }
+}
@@ -0,0 +1,135 @@
+// Building the .pyc header that PyInstaller strips from every code entry
+//
+//  2.x - 3.2   magic, mtime                  (8 bytes)
+//  3.3 - 3.6   magic, mtime, source size     (12 bytes)
+//  3.7+        magic, flags, mtime, size     (16 bytes, PEP 552)
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PythonVersion {
+    pub major: u8,
+    pub minor: u8
+}
+
+impl PythonVersion {
+    pub const fn new(major: u8, minor: u8) -> Self {
+        PythonVersion { major, minor }
+    }
+
+    // The cookie stores `major * 100 + minor`, PyInstaller 2.x and older used `major * 10 + minor`
+    pub fn from_cookie(raw: u32) -> Option<Self> {
+        let (major, minor) = if raw >= 100 { (raw / 100, raw % 100) } else { (raw / 10, raw % 10) };
+
+        if !(2..=3).contains(&major) {
+            return None;
+        }
+
+        Some(PythonVersion::new(major as u8, minor as u8))
+    }
+}
+
+impl fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+struct MagicRange {
+    version: PythonVersion,
+    first: u16,
+    last: u16,
+    // Magic of the final release, used when only the version is known
+    release: u16
+}
+
+const fn magic_range(major: u8, minor: u8, first: u16, last: u16, release: u16) -> MagicRange {
+    MagicRange { version: PythonVersion::new(major, minor), first, last, release }
+}
+
+// Every magic a CPython release line used, including its alphas, betas and release candidates
+const MAGIC_TABLE: &[MagicRange] = &[
+    magic_range(2, 7, 62171, 62211, 62211),
+    magic_range(3, 0, 3000, 3131, 3131),
+    magic_range(3, 1, 3141, 3151, 3151),
+    magic_range(3, 2, 3160, 3180, 3180),
+    magic_range(3, 3, 3190, 3230, 3230),
+    magic_range(3, 4, 3250, 3310, 3310),
+    magic_range(3, 5, 3320, 3351, 3351),
+    magic_range(3, 6, 3360, 3379, 3379),
+    magic_range(3, 7, 3390, 3394, 3394),
+    magic_range(3, 8, 3400, 3413, 3413),
+    magic_range(3, 9, 3420, 3425, 3425),
+    magic_range(3, 10, 3430, 3439, 3439),
+    magic_range(3, 11, 3450, 3495, 3495),
+    magic_range(3, 12, 3500, 3531, 3531),
+    magic_range(3, 13, 3550, 3571, 3571),
+    magic_range(3, 14, 3600, 3649, 3627),
+];
+
+// Numeric part of the magic, every magic ends with `\r\n`
+pub fn magic_number(magic: [u8; 4]) -> Option<u16> {
+    if magic[2..] != [b'\r', b'\n'] {
+        return None;
+    }
+    Some(u16::from_le_bytes([magic[0], magic[1]]))
+}
+
+pub fn version_for_magic(magic: [u8; 4]) -> Option<PythonVersion> {
+    let number = magic_number(magic)?;
+    MAGIC_TABLE.iter()
+        .find(|range| (range.first..=range.last).contains(&number))
+        .map(|range| range.version)
+}
+
+pub fn magic_for_version(version: PythonVersion) -> Option<[u8; 4]> {
+    let range = MAGIC_TABLE.iter().find(|range| range.version == version)?;
+    let [lo, hi] = range.release.to_le_bytes();
+    Some([lo, hi, b'\r', b'\n'])
+}
+
+// Version from a bundled interpreter library name: `python311.dll`, `python27.dll`,
+// `libpython3.11.so.1.0`, `libpython3.9.dylib`, `libpython2.7.so`
+pub fn version_from_library_name(name: &str) -> Option<PythonVersion> {
+    let file_name = name.rsplit(['/', '\\']).next()?.to_ascii_lowercase();
+
+    let parse = |major: &str, minor: &str| -> Option<PythonVersion> {
+        let version = PythonVersion::new(major.parse().ok()?, minor.parse().ok()?);
+        magic_for_version(version).map(|_| version)
+    };
+
+    if let Some(rest) = file_name.strip_prefix("libpython") {
+        let digits: String = rest.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+        let mut parts = digits.split('.');
+        return parse(parts.next()?, parts.next()?);
+    }
+
+    let rest = file_name.strip_prefix("python")?;
+    let digits = rest.strip_suffix(".dll")?;
+    if digits.len() < 2 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    parse(&digits[..1], &digits[1..])
+}
+
+pub fn header_size(version: PythonVersion) -> usize {
+    match (version.major, version.minor) {
+        (2, _) => 8,
+        (3, 0..=2) => 8,
+        (3, 3..=6) => 12,
+        _ => 16,
+    }
+}
+
+// Full header for the given magic, the layout comes from the magic itself and falls back on
+// the python version of the cookie when the magic is not a known CPython one
+pub fn build_header(magic: [u8; 4], version: Option<PythonVersion>) -> Vec<u8> {
+    let size = version_for_magic(magic)
+        .or(version)
+        .map(header_size)
+        .unwrap_or(16);
+
+    let mut header = vec![0u8; size];
+    header[..4].copy_from_slice(&magic);
+    header
+}
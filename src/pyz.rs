@@ -0,0 +1,198 @@
+use std::io::Cursor;
+use binrw::BinRead;
+
+use crate::crypto::PyzCipher;
+use crate::error::{self, Error, Result};
+use crate::marshal::{self, Value};
+use crate::pyc;
+use crate::zlib_inflate;
+
+const PYZ_MAGIC: [u8; 4] = [b'P', b'Y', b'Z', 0];
+
+// PYZ_ITEM_MODULE = 0, anything that is not a package or data is treated as a module
+const PYZ_ITEM_PKG: u8      = 1;
+const PYZ_ITEM_DATA: u8     = 2;
+const PYZ_ITEM_NSPKG: u8    = 3;
+
+#[derive(BinRead, Debug, Clone)]
+#[br(big)]
+pub struct PyzHeader {
+    pub magic: [u8; 4],
+    pub version: [u8; 4],
+    pub toc_offset: u32
+}
+
+#[derive(Debug, Clone)]
+pub struct PyzMember {
+    pub name: String,
+    pub type_: u8,
+    pub offset: usize,
+    pub size: usize
+}
+
+impl PyzMember {
+    /// Relative output path, `a.b` -> `a/b.pyc`, packages -> `a/b/__init__.pyc`
+    pub fn path(&self) -> String {
+        let base = self.name.replace('.', "/");
+        match self.type_ {
+            PYZ_ITEM_PKG | PYZ_ITEM_NSPKG => format!("{}/__init__.pyc", base),
+            PYZ_ITEM_DATA => base,
+            _ => format!("{}.pyc", base),
+        }
+    }
+
+    pub fn is_code(&self) -> bool {
+        self.type_ != PYZ_ITEM_DATA
+    }
+
+    pub fn is_package(&self) -> bool {
+        matches!(self.type_, PYZ_ITEM_PKG | PYZ_ITEM_NSPKG)
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.type_ {
+            PYZ_ITEM_PKG => "package",
+            PYZ_ITEM_DATA => "data",
+            PYZ_ITEM_NSPKG => "nspackage",
+            _ => "module",
+        }
+    }
+}
+
+fn parse_header(data: &[u8]) -> Result<PyzHeader> {
+    let header = PyzHeader::read(&mut Cursor::new(data))
+        .map_err(|e| Error::InvalidPyz(e.to_string()))?;
+
+    if header.magic != PYZ_MAGIC {
+        return Err(Error::InvalidPyz("bad magic".to_string()));
+    }
+
+    Ok(header)
+}
+
+fn parse_member(name: &Value, info: &Value) -> Result<PyzMember> {
+    let invalid = || Error::InvalidPyz("bad toc entry".to_string());
+
+    let name = name.as_str().ok_or_else(invalid)?;
+    let fields = match info {
+        Value::Tuple(items) | Value::List(items) if items.len() == 3 => items,
+        _ => return Err(invalid()),
+    };
+
+    // Before PyInstaller 6 the first field is an `ispkg` flag, the values line up with the typecodes
+    let type_ = fields[0].as_int().ok_or_else(invalid)?;
+    let offset = fields[1].as_int().ok_or_else(invalid)?;
+    let size = fields[2].as_int().ok_or_else(invalid)?;
+
+    if !(0..=u8::MAX as i64).contains(&type_) || offset < 0 || size < 0 {
+        return Err(invalid());
+    }
+
+    Ok(PyzMember {
+        name,
+        type_: type_ as u8,
+        offset: offset as usize,
+        size: size as usize
+    })
+}
+
+fn parse_toc(data: &[u8], header: &PyzHeader) -> Result<Vec<PyzMember>> {
+    let toc_data = data.get(header.toc_offset as usize..)
+        .ok_or(Error::OutOfRange { start: header.toc_offset as u64, end: header.toc_offset as u64, limit: data.len() as u64 })?;
+
+    // Python 2 names are interned strings repeated as `R` references, only read with the version
+    let toc = match pyc::version_for_magic(header.version) {
+        Some(version) => marshal::loads_code(toc_data, version)?,
+        None => marshal::loads(toc_data)?,
+    };
+
+    // Older versions store a list of (name, info) tuples, newer ones a dict
+    let pairs: Vec<(Value, Value)> = match toc {
+        Value::Dict(items) => items,
+        Value::List(items) => items.into_iter().filter_map(|item| match item {
+            Value::Tuple(mut pair) if pair.len() == 2 => {
+                let info = pair.pop().unwrap();
+                let name = pair.pop().unwrap();
+                Some((name, info))
+            }
+            _ => None,
+        }).collect(),
+        _ => return Err(Error::InvalidPyz("unexpected toc type".to_string())),
+    };
+
+    pairs.iter().map(|(name, info)| parse_member(name, info)).collect()
+}
+
+/// A parsed PYZ archive, the members are zlib compressed marshalled code objects without pyc header
+pub struct Pyz<'a> {
+    data: &'a [u8],
+    pub header: PyzHeader,
+    members: Vec<PyzMember>
+}
+
+impl<'a> Pyz<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let header = parse_header(data)?;
+        let members = parse_toc(data, &header)?;
+
+        Ok(Pyz { data, header, members })
+    }
+
+    pub fn members(&self) -> &[PyzMember] {
+        &self.members
+    }
+
+    pub fn find(&self, name: &str) -> Option<&PyzMember> {
+        self.members.iter().find(|member| member.name == name)
+    }
+
+    /// Stored bytes of a member, compressed and possibly encrypted
+    pub fn member_data(&self, member: &PyzMember) -> Result<&'a [u8]> {
+        error::slice(self.data, member.offset, member.size)
+    }
+
+    /// Decompressed member, a member that is not a valid zlib stream is decrypted with `cipher`
+    pub fn read_member(&self, member: &PyzMember, cipher: Option<&PyzCipher>) -> Result<Vec<u8>> {
+        let content = self.member_data(member)?;
+
+        zlib_inflate(content).or_else(|e| {
+            cipher.and_then(|cipher| cipher.decrypt_and_inflate(content))
+                .ok_or(e)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    // marshal.dumps([(n, (1, 12, 5)), (intern('pkg.mod'), (0, 17, 6)), (n, (0, 23, 1))]) with n = intern('pkg') on 2.7
+    const PYTHON2_TOC: &str = "5b0300000028020000007403000000706b6728030000006901000000690c000000690500000028020000007407000000\
+        706b672e6d6f642803000000690000000069110000006906000000280200000052000000002803000000690000000069170000006901000000";
+
+    fn pyz(magic: &str, toc: &[u8]) -> Vec<u8> {
+        [b"PYZ\0".as_slice(), &hex(magic), &12u32.to_be_bytes(), toc].concat()
+    }
+
+    #[test]
+    fn python2_tocs_are_read_with_their_string_refs() {
+        let data = pyz("03f30d0a", &hex(PYTHON2_TOC));
+        let pyz = Pyz::parse(&data).unwrap();
+
+        let members: Vec<(&str, u8, usize, usize)> = pyz.members().iter()
+            .map(|member| (member.name.as_str(), member.type_, member.offset, member.size))
+            .collect();
+        assert_eq!(members, [("pkg", 1, 12, 5), ("pkg.mod", 0, 17, 6), ("pkg", 0, 23, 1)]);
+    }
+
+    #[test]
+    fn tocs_of_unknown_versions_are_read_without_one() {
+        // marshal.dumps({'mod': (0, 12, 5)}) on 3.12
+        let data = pyz("00000000", &hex("7bda036d6f64a903e900000000e90c000000e90500000030"));
+        assert_eq!(Pyz::parse(&data).unwrap().members()[0].name, "mod");
+    }
+}
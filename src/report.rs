@@ -0,0 +1,218 @@
+// JSON description of an archive, with the extraction status of every entry when there was one
+
+use std::collections::{HashMap, HashSet};
+
+use crate::archive::{Archive, PyinstEntry, ARCHIVE_ITEM_PYZ, ARCHIVE_ITEM_RUNTIME_OPTION, ARCHIVE_ITEM_SPLASH};
+use crate::error::Error;
+use crate::extract::{EmbeddedArchive, ExtractReport};
+use crate::json::Json;
+use crate::runtime::RuntimeOption;
+use crate::salvage::SalvageReport;
+use crate::splash::Splash;
+use crate::verify::VerifyReport;
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+struct Statuses<'a> {
+    failures: HashMap<&'a str, &'a Error>,
+    skipped: HashSet<&'a str>,
+    encrypted: HashSet<&'a str>
+}
+
+impl<'a> Statuses<'a> {
+    fn new(report: &'a ExtractReport) -> Self {
+        Statuses {
+            failures: report.failures.iter().map(|(name, e)| (name.as_str(), e)).collect(),
+            skipped: report.skipped.iter().map(String::as_str).collect(),
+            encrypted: report.encrypted.iter().map(String::as_str).collect(),
+        }
+    }
+
+    fn annotate(&self, value: &mut Json, name: &str) {
+        if let Some(e) = self.failures.get(name) {
+            value.insert("status", "failed".into());
+            value.insert("error", e.to_string().into());
+        } else if self.skipped.contains(name) {
+            value.insert("status", "skipped".into());
+        } else if self.encrypted.contains(name) {
+            value.insert("status", "encrypted".into());
+        } else {
+            value.insert("status", "extracted".into());
+        }
+    }
+}
+
+fn option_json(option: &RuntimeOption) -> Json {
+    Json::object([
+        ("name", option.name.as_str().into()),
+        ("value", option.value.clone().into()),
+        ("kind", option.kind.describe().into()),
+        ("description", option.describe().into()),
+    ])
+}
+
+fn splash_json(splash: &Splash) -> Json {
+    Json::object([
+        ("tcl_libname", splash.tcl_libname.as_str().into()),
+        ("tk_libname", splash.tk_libname.as_str().into()),
+        ("tk_lib", splash.tk_lib.as_str().into()),
+        ("rundir", splash.rundir.as_str().into()),
+        ("image_format", splash.image_extension().into()),
+        ("image_size", splash.image.len().into()),
+        ("script_size", splash.script.len().into()),
+        ("requirements", Json::Array(splash.requirements.iter().map(|name| name.as_str().into()).collect())),
+    ])
+}
+
+fn entry_json(archive: &Archive, entry: &PyinstEntry, statuses: Option<&Statuses>) -> Json {
+    let mut value = Json::object([
+        ("name", entry.name.as_str().into()),
+        ("type", (entry.type_ as char).to_string().into()),
+        ("type_name", entry.type_name().into()),
+        ("compression_flag", entry.compression_flag.into()),
+        ("compressed_size", entry.compressed_size.into()),
+        ("uncompressed_size", entry.uncompressed_size.into()),
+        ("offset", entry.offset.into()),
+    ]);
+
+    // Runtime options are not extracted, they are described instead
+    if entry.type_ == ARCHIVE_ITEM_RUNTIME_OPTION {
+        value.insert("option", option_json(&RuntimeOption::parse(&entry.name)));
+    } else if let Some(statuses) = statuses {
+        statuses.annotate(&mut value, &entry.name);
+    }
+
+    if entry.type_ == ARCHIVE_ITEM_SPLASH {
+        match archive.read_entry(entry).and_then(|data| Splash::parse(&data)) {
+            Ok(splash) => value.insert("splash", splash_json(&splash)),
+            Err(e) => value.insert("splash_error", e.to_string().into()),
+        }
+    }
+
+    if entry.type_ == ARCHIVE_ITEM_PYZ {
+        match archive.pyz(entry) {
+            Ok(pyz) => {
+                let members = pyz.members().iter().map(|member| {
+                    let mut member_value = Json::object([
+                        ("name", member.name.as_str().into()),
+                        ("type", member.type_.into()),
+                        ("type_name", member.type_name().into()),
+                        ("offset", member.offset.into()),
+                        ("size", member.size.into()),
+                    ]);
+                    if let Some(statuses) = statuses {
+                        statuses.annotate(&mut member_value, &format!("{}/{}", entry.name, member.name));
+                    }
+                    member_value
+                }).collect();
+
+                value.insert("pyc_magic", hex(&pyz.header.version).into());
+                value.insert("members", Json::Array(members));
+            }
+            Err(e) => value.insert("pyz_error", e.to_string().into()),
+        }
+    }
+
+    value
+}
+
+/// Cookie, offsets, python version, pyc magic and every entry including PYZ members
+pub fn archive_json(archive: &Archive, extraction: Option<&ExtractReport>) -> Json {
+    let header = archive.header();
+    let statuses = extraction.map(Statuses::new);
+
+    let cookie = Json::object([
+        ("offset", archive.cookie_offset().into()),
+        ("layout", format!("{:?}", header.layout).into()),
+        ("signature", hex(&header.signature).into()),
+        ("magic_modified", archive.magic_modified().into()),
+        ("package_size", header.package_size.into()),
+        ("toc_offset", header.toc_offset.into()),
+        ("toc_size", header.toc_size.into()),
+        ("python_version", header.python_version.into()),
+        ("pylib_name", header.pylib_name.clone().into()),
+    ]);
+
+    let pyc_magic = match archive.pyc_magic() {
+        Some((magic, source)) => Json::object([
+            ("value", hex(&magic).into()),
+            ("source", source.describe().into()),
+        ]),
+        None => Json::Null,
+    };
+
+    let entries = archive.entries().iter()
+        .map(|entry| entry_json(archive, entry, statuses.as_ref()))
+        .collect();
+
+    let mut value = Json::object([
+        ("file_size", archive.data().len().into()),
+        ("cookie", cookie),
+        ("overlay_offset", archive.overlay_offset().into()),
+        ("tail_size", archive.tail_size().into()),
+        ("python_version", header.python_version().map(|version| version.to_string()).into()),
+        ("pyc_magic", pyc_magic),
+        ("runtime_options", Json::Array(archive.runtime_options().iter().map(option_json).collect())),
+        ("entries", Json::Array(entries)),
+    ]);
+
+    if let Some(report) = extraction {
+        value.insert("summary", summary_json(report));
+    }
+
+    value
+}
+
+fn summary_json(report: &ExtractReport) -> Json {
+    Json::object([
+        ("written", report.written.into()),
+        ("skipped", report.skipped.len().into()),
+        ("failed", report.failures.len().into()),
+        ("encrypted", report.encrypted.len().into()),
+    ])
+}
+
+fn failures_json(failures: &[(String, Error)]) -> Json {
+    Json::Array(failures.iter()
+        .map(|(name, e)| Json::object([("name", name.as_str().into()), ("error", e.to_string().into())]))
+        .collect())
+}
+
+/// Where each embedded archive was found and how its extraction went
+pub fn embedded_json(archives: &[EmbeddedArchive]) -> Json {
+    let archives = archives.iter().map(|archive| {
+        Json::object([
+            ("location", archive.location.as_str().into()),
+            ("cookie_offset", archive.cookie_offset.into()),
+            ("entries", archive.entries.into()),
+            ("output", archive.output.display().to_string().into()),
+            ("summary", summary_json(&archive.report)),
+            ("failures", failures_json(&archive.report.failures)),
+        ])
+    }).collect();
+
+    Json::object([("archives", Json::Array(archives))])
+}
+
+/// What a salvage recovered from a damaged archive
+pub fn salvage_json(report: &SalvageReport) -> Json {
+    Json::object([
+        ("toc_entries", report.toc_entries.into()),
+        ("pyz", report.pyz.into()),
+        ("carved", report.carved.into()),
+        ("summary", summary_json(&report.extraction)),
+        ("failures", failures_json(&report.extraction.failures)),
+    ])
+}
+
+/// Outcome of an integrity check
+pub fn verify_json(report: &VerifyReport) -> Json {
+    Json::object([
+        ("ok", report.is_ok().into()),
+        ("entries", report.entries.into()),
+        ("members", report.members.into()),
+        ("issues", failures_json(&report.issues)),
+    ])
+}
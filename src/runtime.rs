@@ -0,0 +1,58 @@
+// Runtime option entries ('o'): the whole option is the entry name and the payload is empty.
+// Options starting with `pyi-` are read by the bootloader, the others are passed to the interpreter
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    Interpreter,
+    Bootloader
+}
+
+impl OptionKind {
+    pub fn describe(self) -> &'static str {
+        match self {
+            OptionKind::Interpreter => "interpreter",
+            OptionKind::Bootloader => "bootloader",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RuntimeOption {
+    pub name: String,
+    pub value: Option<String>,
+    pub kind: OptionKind
+}
+
+impl RuntimeOption {
+    // The value follows a space (`W ignore`, `pyi-runtime-tmpdir /tmp`) or an `=` (`hash_seed=0`)
+    pub fn parse(entry_name: &str) -> Self {
+        let (name, value) = match entry_name.split_once(' ').or_else(|| entry_name.split_once('=')) {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (entry_name, None),
+        };
+
+        let kind = if name.starts_with("pyi-") { OptionKind::Bootloader } else { OptionKind::Interpreter };
+
+        RuntimeOption { name: name.to_string(), value, kind }
+    }
+
+    /// What the option does, for the options PyInstaller knows
+    pub fn describe(&self) -> Option<&'static str> {
+        Some(match self.name.as_str() {
+            "v" => "verbose imports",
+            "u" => "unbuffered stdio",
+            "O" => "optimized bytecode",
+            "W" => "warning filter",
+            "X" => "implementation option",
+            "hash_seed" => "fixed hash seed",
+            "pyi-runtime-tmpdir" => "extraction directory",
+            "pyi-bootloader-ignore-signals" => "bootloader ignores signals",
+            "pyi-windows-manifest-filename" => "windows manifest",
+            "pyi-contents-directory" => "contents directory",
+            "pyi-macos-argv-emulation" => "macOS argv emulation",
+            "pyi-disable-windowed-traceback" => "windowed traceback disabled",
+            "pyi-hide-console" => "console window mode",
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,454 @@
+// Recovery of archives whose cookie or TOC is damaged: TOC records, PYZ archives and zlib streams
+// are searched in the whole file without relying on the cookie
+
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use memchr::memmem;
+
+use crate::archive::{parse_entry, PyinstEntry, ARCHIVE_ITEM_PYSOURCE, ARCHIVE_ITEM_PYZ, ARCHIVE_ITEM_RUNTIME_OPTION, ENTRY_FIXED_SIZE};
+use crate::error::{self, Result};
+use crate::extract::{self, output_path, ExtractOptions, ExtractReport};
+use crate::pyz::Pyz;
+use crate::{is_zlib_header, pyc, zlib_decompress, zlib_inflate_prefix};
+
+const MAX_NAME_SIZE: usize = 4096;
+
+// A TOC record that looks like one written by PyInstaller: a printable name padded with NULs,
+// a known type and sizes that agree with the compression flag
+fn plausible_entry(data: &[u8], pos: usize) -> Option<PyinstEntry> {
+    let size = u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().unwrap()) as usize;
+    if size <= ENTRY_FIXED_SIZE || size > ENTRY_FIXED_SIZE + MAX_NAME_SIZE {
+        return None;
+    }
+
+    let entry = parse_entry(data, pos, 0).ok()?;
+    let name_field = &data[pos + ENTRY_FIXED_SIZE..pos + size];
+    let name_len = name_field.iter().position(|&b| b == 0)?;
+
+    let plausible = name_len > 0
+        && std::str::from_utf8(&name_field[..name_len]).is_ok_and(|name| !name.chars().any(char::is_control))
+        && name_field[name_len..].iter().all(|&b| b == 0)
+        && entry.compression_flag <= 1
+        && entry.type_name() != "unknown"
+        && (entry.is_compressed() || entry.compressed_size == entry.uncompressed_size)
+        && entry.compressed_size as usize <= data.len();
+
+    plausible.then_some(entry)
+}
+
+/// Runs of consecutive TOC records with the offset of the first one, entry offsets are left
+/// relative to the package since its start is not known
+pub fn find_toc(data: &[u8]) -> Vec<(usize, Vec<PyinstEntry>)> {
+    let mut tocs = Vec::new();
+    let mut pos = 0;
+
+    while pos + ENTRY_FIXED_SIZE < data.len() {
+        let Some(first) = plausible_entry(data, pos) else {
+            pos += 1;
+            continue;
+        };
+
+        let toc_start = pos;
+        pos += first.size as usize;
+
+        let mut entries = vec![first];
+        while let Some(entry) = plausible_entry(data, pos) {
+            pos += entry.size as usize;
+            entries.push(entry);
+        }
+
+        tocs.push((toc_start, entries));
+    }
+
+    tocs
+}
+
+/// A zlib stream found in the file
+pub struct Carved {
+    pub offset: usize,
+    /// Compressed size, header and checksum included
+    pub size: usize,
+    pub data: Vec<u8>
+}
+
+/// Iterator over the zlib streams of a buffer, in order. Streams are not searched inside one another
+pub struct Streams<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+pub fn carve_streams(data: &[u8]) -> Streams<'_> {
+    Streams { data, pos: 0 }
+}
+
+// The stream ends with the adler32 of its output, the first occurrence that inflates is the end
+fn stream_size(data: &[u8], output: &[u8]) -> Option<usize> {
+    let checksum = libdeflater::adler32(output).to_be_bytes();
+
+    memmem::find_iter(&data[2..], &checksum)
+        .map(|pos| pos + 2 + checksum.len())
+        .find(|&end| zlib_inflate_prefix(&data[..end]).is_ok())
+}
+
+impl Iterator for Streams<'_> {
+    type Item = Carved;
+
+    fn next(&mut self) -> Option<Carved> {
+        while self.pos + 6 <= self.data.len() {
+            let offset = self.pos;
+            self.pos += 1;
+
+            if !is_zlib_header(self.data[offset], self.data[offset + 1]) {
+                continue;
+            }
+
+            let rest = &self.data[offset..];
+            let Ok(output) = zlib_inflate_prefix(rest) else { continue };
+            let Some(size) = stream_size(rest, &output) else { continue };
+
+            self.pos = offset + size;
+            return Some(Carved { offset, size, data: output });
+        }
+
+        None
+    }
+}
+
+/// Outcome of `salvage`
+#[derive(Debug, Default)]
+pub struct SalvageReport {
+    /// TOC records that could be parsed
+    pub toc_entries: usize,
+    /// PYZ archives found by their magic
+    pub pyz: usize,
+    /// Streams that match no TOC record, written as `carved_<offset>.bin`
+    pub carved: usize,
+    /// Named entries and PYZ members
+    pub extraction: ExtractReport
+}
+
+fn write_recovered(base_path: &Path, entry: &PyinstEntry, contents: &[u8], pyc_header: &[u8]) -> Result<()> {
+    let full_path = output_path(base_path, &entry.name)?;
+
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if entry.is_compressed() && entry.type_ == ARCHIVE_ITEM_PYSOURCE {
+        fs::write(full_path, [pyc_header, contents].concat())?;
+    } else {
+        fs::write(full_path, contents)?;
+    }
+    Ok(())
+}
+
+fn extract_recovered_pyz(base_path: &Path, name: &str, pyz: &Pyz, options: &ExtractOptions) -> ExtractReport {
+    match output_path(base_path, name) {
+        Ok(path) => {
+            let mut out_dir = path.into_os_string();
+            out_dir.push("_extracted");
+            extract::extract_pyz(Path::new(&out_dir), name, pyz, options)
+        }
+        Err(e) => ExtractReport { failures: vec![(name.to_string(), e)], ..Default::default() },
+    }
+}
+
+// Start of the package the entries are relative to. PyInstaller writes the TOC right after the entries,
+// which is right when the first records survived, otherwise a compressed entry is looked for before
+// the TOC. A start is only kept when every compressed entry inflates there and every PYZ parses
+fn package_start(data: &[u8], toc_start: usize, entries: &[PyinstEntry]) -> Option<u64> {
+    let fits = |overlay_offset: u64| {
+        let mut checked = 0;
+        let all = entries.iter().all(|entry| {
+            let Ok(raw) = error::range(data, overlay_offset + entry.offset, entry.compressed_size as u64) else {
+                return false;
+            };
+            if entry.is_compressed() {
+                checked += 1;
+                zlib_decompress(raw, entry.uncompressed_size as usize).is_ok()
+            } else if entry.type_ == ARCHIVE_ITEM_PYZ {
+                checked += 1;
+                Pyz::parse(raw).is_ok()
+            } else {
+                true
+            }
+        });
+        all && checked > 0
+    };
+
+    let end = entries.iter().map(|entry| entry.offset + entry.compressed_size as u64).max()?;
+    if let Some(overlay_offset) = (toc_start as u64).checked_sub(end) && fits(overlay_offset) {
+        return Some(overlay_offset);
+    }
+
+    let anchor = entries.iter().find(|entry| entry.is_compressed())?;
+    (0..toc_start.saturating_sub(1))
+        .filter(|&pos| is_zlib_header(data[pos], data[pos + 1]))
+        .filter_map(|pos| (pos as u64).checked_sub(anchor.offset))
+        .find(|&overlay_offset| fits(overlay_offset))
+}
+
+/// Writes whatever can be recovered from a damaged archive under `base_path`: the entries of the TOC
+/// records found, the members of the PYZ archives, then every zlib stream no record accounts for
+/// as `carved_<offset>.bin`. An empty `options.pyc_header` is replaced by one built from a PYZ magic
+pub fn salvage(base_path: &Path, data: &[u8], mut options: ExtractOptions) -> SalvageReport {
+    let mut report = SalvageReport::default();
+    let mut claimed: Vec<Range<usize>> = Vec::new();
+
+    let pyz_offsets: Vec<usize> = memmem::find_iter(data, b"PYZ\0").collect();
+
+    if options.pyc_header.is_empty() && let Some(pyz) = pyz_offsets.iter().find_map(|&pos| Pyz::parse(&data[pos..]).ok()) {
+        options.pyc_header = pyc::build_header(pyz.header.version, pyc::version_for_magic(pyz.header.version));
+    }
+
+    // Compressed entries that could not be located are matched against the carved streams by their sizes
+    let mut unlocated: HashMap<(usize, usize), Vec<PyinstEntry>> = HashMap::new();
+
+    for (toc_start, entries) in find_toc(data) {
+        report.toc_entries += entries.len();
+        let overlay_offset = package_start(data, toc_start, &entries);
+
+        for entry in entries {
+            let located = overlay_offset.and_then(|overlay_offset| {
+                let start = overlay_offset + entry.offset;
+                let raw = error::range(data, start, entry.compressed_size as u64).ok()?;
+                Some((start as usize..start as usize + raw.len(), raw))
+            });
+
+            let contents = located.as_ref().and_then(|(_, raw)| if entry.is_compressed() {
+                zlib_decompress(raw, entry.uncompressed_size as usize).ok()
+            } else {
+                Some(raw.to_vec())
+            });
+
+            let (Some((range, raw)), Some(contents)) = (located, contents) else {
+                if entry.is_compressed() {
+                    unlocated.entry((entry.compressed_size as usize, entry.uncompressed_size as usize)).or_default().push(entry);
+                }
+                continue;
+            };
+
+            claimed.push(range);
+
+            if entry.type_ == ARCHIVE_ITEM_RUNTIME_OPTION {
+                continue;
+            }
+
+            if entry.type_ == ARCHIVE_ITEM_PYZ && let Ok(pyz) = Pyz::parse(raw) {
+                report.extraction.merge(extract_recovered_pyz(base_path, &entry.name, &pyz, &options));
+            }
+
+            if options.filter.matches_entry(&entry) {
+                report.extraction.record(&entry.name, write_recovered(base_path, &entry, &contents, &options.pyc_header));
+            } else {
+                report.extraction.skipped.push(entry.name.clone());
+            }
+        }
+    }
+
+    // PYZ archives the TOC does not account for, their TOC offset is relative to their own start
+    for pos in pyz_offsets {
+        if claimed.iter().any(|range| range.contains(&pos)) {
+            continue;
+        }
+        let Ok(pyz) = Pyz::parse(&data[pos..]) else { continue };
+
+        let end = pos + pyz.header.toc_offset as usize;
+        report.pyz += 1;
+        report.extraction.merge(extract_recovered_pyz(base_path, &format!("PYZ_{:#X}.pyz", pos), &pyz, &options));
+        claimed.push(pos..end);
+    }
+
+    for stream in carve_streams(data) {
+        if claimed.iter().any(|range| range.contains(&stream.offset)) {
+            continue;
+        }
+
+        let named = unlocated.get_mut(&(stream.size, stream.data.len())).and_then(Vec::pop);
+        let (entry, result) = match named {
+            Some(entry) if !options.filter.matches_entry(&entry) => {
+                report.extraction.skipped.push(entry.name);
+                continue;
+            }
+            Some(entry) => {
+                let result = write_recovered(base_path, &entry, &stream.data, &options.pyc_header);
+                (entry.name, result)
+            }
+            None => {
+                report.carved += 1;
+                let name = format!("carved_{:#X}.bin", stream.offset);
+                if !options.filter.matches_carved(&name) {
+                    report.extraction.skipped.push(name);
+                    continue;
+                }
+                let result = fs::create_dir_all(base_path)
+                    .and_then(|_| fs::write(base_path.join(&name), &stream.data))
+                    .map_err(Into::into);
+                (name, result)
+            }
+        };
+        report.extraction.record(&entry, result);
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::{ARCHIVE_ITEM_DATA, ARCHIVE_ITEM_PYSOURCE};
+    use crate::filter::{Filter, Glob, Pattern};
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut compressor = libdeflater::Compressor::new(libdeflater::CompressionLvl::default());
+        let mut output = vec![0; compressor.zlib_compress_bound(data.len())];
+        let size = compressor.zlib_compress(data, &mut output).unwrap();
+        output.truncate(size);
+        output
+    }
+
+    // A TOC record as PyInstaller writes it, the name padded with NULs to a multiple of 16
+    fn record(name: &str, offset: usize, stored: usize, size: usize, compressed: bool, type_: u8) -> Vec<u8> {
+        let name_size = (name.len() + 1).next_multiple_of(16);
+        let mut record = ((ENTRY_FIXED_SIZE + name_size) as u32).to_be_bytes().to_vec();
+        for value in [offset, stored, size] {
+            record.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        record.extend_from_slice(&[compressed as u8, type_]);
+        record.extend_from_slice(name.as_bytes());
+        record.resize(ENTRY_FIXED_SIZE + name_size, 0);
+        record
+    }
+
+    const STUB: &[u8] = b"MZ executable stub before the package ";
+
+    // The stub, the entries, their TOC then a zeroed cookie. Returns the records with the data
+    fn sample() -> (Vec<u8>, Vec<Vec<u8>>) {
+        let entries: [(&str, &[u8], bool, u8); 4] = [
+            ("main", b"print('hello')\n", true, ARCHIVE_ITEM_PYSOURCE),
+            ("lib/data.txt", b"some data, some data, some data\n", true, ARCHIVE_ITEM_DATA),
+            ("README", b"stored as is", false, ARCHIVE_ITEM_DATA),
+            ("lib/other.txt", b"other data, other data\n", true, ARCHIVE_ITEM_DATA),
+        ];
+
+        let mut data = STUB.to_vec();
+        let mut records = Vec::new();
+        for (name, contents, compressed, type_) in entries {
+            let stored = if compressed { zlib(contents) } else { contents.to_vec() };
+            records.push(record(name, data.len() - STUB.len(), stored.len(), contents.len(), compressed, type_));
+            data.extend_from_slice(&stored);
+        }
+        data.extend(records.concat());
+        data.extend_from_slice(&[0; 88]);
+
+        (data, records)
+    }
+
+    fn names(entries: &[PyinstEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn toc_before_a_zeroed_cookie_is_found() {
+        let (data, records) = sample();
+        let toc_start = data.len() - 88 - records.concat().len();
+
+        let tocs = find_toc(&data);
+        assert_eq!(tocs.len(), 1);
+        let (start, entries) = &tocs[0];
+        assert_eq!(*start, toc_start);
+        // Scripts get their `.pyc` suffix like in a TOC read from the cookie
+        assert_eq!(names(entries), ["main.pyc", "lib/data.txt", "README", "lib/other.txt"]);
+
+        // Right after the entries, as PyInstaller writes it
+        assert_eq!(package_start(&data, toc_start, entries), Some(STUB.len() as u64));
+    }
+
+    #[test]
+    fn toc_without_its_first_records_is_found() {
+        let (mut data, records) = sample();
+        let toc_start = data.len() - 88 - records.concat().len();
+        let destroyed = records[0].len() + records[1].len();
+        data[toc_start..toc_start + destroyed].fill(0xEE);
+
+        let tocs = find_toc(&data);
+        assert_eq!(tocs.len(), 1);
+        let (start, entries) = &tocs[0];
+        assert_eq!(*start, toc_start + destroyed);
+        assert_eq!(names(entries), ["README", "lib/other.txt"]);
+
+        // The TOC no longer starts where the entries end, the start is found from a compressed entry
+        assert_eq!(package_start(&data, *start, entries), Some(STUB.len() as u64));
+    }
+
+    #[test]
+    fn package_start_needs_an_entry_to_check() {
+        let (data, records) = sample();
+        let toc_start = data.len() - 88 - records.concat().len();
+        let (_, entries) = &find_toc(&data)[0];
+
+        // Only the stored entry is left, nothing tells where it starts
+        assert_eq!(package_start(&data, toc_start, &entries[2..3]), None);
+    }
+
+    #[test]
+    fn streams_are_carved_with_their_size() {
+        let first = zlib(b"first stream, first stream, first stream");
+        let second = zlib(&[7; 1000]);
+
+        // A zlib header that does not inflate, then the streams between other bytes
+        let before = b"\x78\x9c not deflate ";
+        let data = [before.as_slice(), &first, b" between ", &second, b" after"].concat();
+        let carved: Vec<Carved> = carve_streams(&data).collect();
+
+        assert_eq!(carved.len(), 2);
+        assert_eq!((carved[0].offset, carved[0].size), (before.len(), first.len()));
+        assert_eq!(carved[0].data, b"first stream, first stream, first stream");
+        assert_eq!((carved[1].offset, carved[1].size), (before.len() + first.len() + 9, second.len()));
+        assert_eq!(carved[1].data, [7; 1000]);
+    }
+
+    #[test]
+    fn stream_size_ends_at_the_first_checksum_that_inflates() {
+        let output = b"checksum twice".as_slice();
+        let stream = zlib(output);
+        let checksum = libdeflater::adler32(output).to_be_bytes();
+
+        let data = [stream.as_slice(), &checksum, b"trailing"].concat();
+        assert_eq!(stream_size(&data, output), Some(stream.len()));
+        assert_eq!(stream_size(&stream[..stream.len() - 1], output), None);
+    }
+
+    #[test]
+    fn salvage_applies_the_filter_to_carved_streams() {
+        let base_path = std::env::temp_dir().join(format!("extractor-salvage-{}", std::process::id()));
+
+        // The record points past the file, its stream is only found by its sizes
+        let named = zlib(b"named by its record");
+        let carved = zlib(b"no record for this one");
+        let data = [
+            named.as_slice(),
+            &carved,
+            &record("lib/named.txt", 1 << 20, named.len(), 19, true, ARCHIVE_ITEM_DATA),
+        ].concat();
+
+        let options = |pattern: &str| ExtractOptions {
+            filter: Filter { exclude: vec![Pattern::Glob(Glob::new(pattern).unwrap())], ..Filter::default() },
+            ..ExtractOptions::default()
+        };
+
+        let report = salvage(&base_path, &data, options("*.txt"));
+        assert_eq!(report.extraction.skipped, ["lib/named.txt"]);
+        assert_eq!(report.extraction.written, 1);
+        assert!(!base_path.join("lib/named.txt").exists());
+        assert!(base_path.join(format!("carved_{:#X}.bin", named.len())).exists());
+        fs::remove_dir_all(&base_path).unwrap();
+
+        let report = salvage(&base_path, &data, options("carved_*"));
+        assert_eq!(report.extraction.skipped, [format!("carved_{:#X}.bin", named.len())]);
+        assert_eq!(fs::read(base_path.join("lib/named.txt")).unwrap(), b"named by its record");
+        fs::remove_dir_all(&base_path).unwrap();
+    }
+}
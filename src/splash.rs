@@ -0,0 +1,92 @@
+// Splash screen resources ('l' entry): a header with the Tcl/Tk library names followed by the files
+// to extract before the splash starts, the Tcl script and the image. Offsets are relative to the entry
+
+use crate::error::{Error, Result};
+
+// PyInstaller >= 5 uses 32 byte names for the Tcl and Tk libraries, older versions 16 bytes
+const NAME_LAYOUTS: [[usize; 4]; 2] = [[32, 32, 16, 16], [16, 16, 16, 16]];
+
+#[derive(Debug)]
+pub struct Splash {
+    /// Tcl shared library, e.g. `tcl86t.dll`
+    pub tcl_libname: String,
+    /// Tk shared library, e.g. `tk86t.dll`
+    pub tk_libname: String,
+    /// Directory of the Tk library scripts, e.g. `tk/`
+    pub tk_lib: String,
+    /// Directory of the extraction path the requirements are written to
+    pub rundir: String,
+    /// Tcl script that shows the splash screen
+    pub script: Vec<u8>,
+    pub image: Vec<u8>,
+    /// Files the bootloader extracts before starting the splash screen
+    pub requirements: Vec<String>
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::InvalidSplash(msg.to_string())
+}
+
+// A NUL padded name, names as long as the field have no NUL. The padding is checked so that
+// a layout with the wrong field sizes is refused
+fn name(field: &[u8]) -> Option<String> {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    if field[len..].iter().any(|&b| b != 0) {
+        return None;
+    }
+
+    let name = std::str::from_utf8(&field[..len]).ok()?;
+    (!name.chars().any(char::is_control)).then(|| name.to_string())
+}
+
+fn parse_layout(data: &[u8], layout: &[usize; 4]) -> Option<Splash> {
+    let mut pos = 0;
+    let mut names = Vec::with_capacity(layout.len());
+    for &size in layout {
+        names.push(name(data.get(pos..pos + size)?)?);
+        pos += size;
+    }
+
+    let header_size = pos + 24;
+    let fields: Vec<usize> = data.get(pos..header_size)?
+        .chunks_exact(4)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()) as usize)
+        .collect();
+
+    let section = |len: usize, offset: usize| {
+        (offset >= header_size).then_some(())?;
+        data.get(offset..offset.checked_add(len)?)
+    };
+
+    let script = section(fields[0], fields[1])?;
+    let image = section(fields[2], fields[3])?;
+    let requirements = section(fields[4], fields[5])?;
+
+    let requirements = requirements.split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect();
+
+    let [tcl_libname, tk_libname, tk_lib, rundir]: [String; 4] = names.try_into().ok()?;
+
+    Some(Splash { tcl_libname, tk_libname, tk_lib, rundir, script: script.to_vec(), image: image.to_vec(), requirements })
+}
+
+impl Splash {
+    /// Parses the decompressed contents of a splash entry
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        NAME_LAYOUTS.iter()
+            .find_map(|layout| parse_layout(data, layout))
+            .ok_or_else(|| invalid("bad header"))
+    }
+
+    /// Extension matching the image format, PyInstaller converts the image to PNG when it can
+    pub fn image_extension(&self) -> &'static str {
+        match self.image.as_slice() {
+            [0x89, b'P', b'N', b'G', ..] => "png",
+            [b'G', b'I', b'F', b'8', ..] => "gif",
+            [0xFF, 0xD8, 0xFF, ..] => "jpg",
+            _ => "bin",
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Integrity check of an archive: every entry and PYZ member is decompressed in memory and compared
+// with the TOC, nothing is written
+
+use rayon::prelude::*;
+
+use crate::archive::{Archive, PyinstEntry, ARCHIVE_ITEM_PYZ};
+use crate::crypto::PyzCipher;
+use crate::error::{Error, Result};
+use crate::zlib_decompress;
+
+/// Outcome of `verify`, an archive is consistent when there are no issues
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub entries: usize,
+    pub members: usize,
+    /// Entry or `pyzname/member` with what is wrong with it
+    pub issues: Vec<(String, Error)>
+}
+
+impl VerifyReport {
+    fn merge(mut self, other: VerifyReport) -> Self {
+        self.entries += other.entries;
+        self.members += other.members;
+        self.issues.extend(other.issues);
+        self
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+// The entries are stored between the start of the package and the TOC
+fn check_bounds(archive: &Archive, entry: &PyinstEntry) -> Result<()> {
+    let start = entry.offset;
+    let end = start + entry.compressed_size as u64;
+    let package_start = archive.overlay_offset() as u64;
+    let toc_start = package_start + archive.header().toc_offset as u64;
+
+    if start < package_start || end > toc_start {
+        return Err(Error::OutsidePackage { start, end });
+    }
+    Ok(())
+}
+
+fn check_entry(archive: &Archive, entry: &PyinstEntry) -> Result<()> {
+    check_bounds(archive, entry)?;
+
+    let raw = archive.raw_entry(entry)?;
+    // Inflated into at most the size of the TOC, a longer stream is a `SizeMismatch` already
+    let actual = if entry.is_compressed() { zlib_decompress(raw, entry.uncompressed_size as usize)?.len() } else { raw.len() };
+
+    if actual != entry.uncompressed_size as usize {
+        return Err(Error::SizeMismatch { expected: entry.uncompressed_size as u64, actual: actual as u64 });
+    }
+    Ok(())
+}
+
+fn verify_entry(archive: &Archive, entry: &PyinstEntry, cipher: Option<&PyzCipher>) -> VerifyReport {
+    let mut report = VerifyReport { entries: 1, ..Default::default() };
+
+    if let Err(e) = check_entry(archive, entry) {
+        report.issues.push((entry.name.clone(), e));
+        return report;
+    }
+
+    if entry.type_ == ARCHIVE_ITEM_PYZ {
+        match archive.pyz(entry) {
+            Ok(pyz) => {
+                report.members = pyz.members().len();
+                report.issues.par_extend(pyz.members().par_iter().filter_map(|member| {
+                    let e = pyz.read_member(member, cipher).err()?;
+                    Some((format!("{}/{}", entry.name, member.name), e))
+                }));
+            }
+            Err(e) => report.issues.push((entry.name.clone(), e)),
+        }
+    }
+
+    report
+}
+
+/// Decompresses every entry and PYZ member, checks the sizes stored in the TOC and that every
+/// entry lies inside the package. `cipher` is needed for encrypted PYZ members
+pub fn verify(archive: &Archive, cipher: Option<&PyzCipher>) -> VerifyReport {
+    let mut report = archive.entries().par_iter()
+        .map(|entry| verify_entry(archive, entry, cipher))
+        .reduce(VerifyReport::default, VerifyReport::merge);
+
+    report.issues.sort_by(|a, b| a.0.cmp(&b.0));
+    report
+}
@@ -0,0 +1,104 @@
+// Minimal zip reader for archives bundled inside the CArchive such as `base_library.zip`
+
+use crate::deflate_decompress;
+use crate::error::{Error, Result};
+
+const EOCD_SIGNATURE: u32           = 0x06054b50;
+const CENTRAL_DIR_SIGNATURE: u32    = 0x02014b50;
+const LOCAL_HEADER_SIGNATURE: u32   = 0x04034b50;
+
+const EOCD_SIZE: usize              = 22;
+const CENTRAL_DIR_SIZE: usize       = 46;
+const LOCAL_HEADER_SIZE: usize      = 30;
+
+const METHOD_STORED: u16            = 0;
+const METHOD_DEFLATED: u16          = 8;
+
+#[derive(Debug)]
+pub struct ZipEntry {
+    pub name: String,
+    pub method: u16,
+    pub compressed_size: usize,
+    pub uncompressed_size: usize,
+    pub header_offset: usize
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::InvalidZip(msg.to_string())
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Result<u16> {
+    data.get(pos..pos + 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or_else(|| invalid("truncated"))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Result<u32> {
+    data.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(|| invalid("truncated"))
+}
+
+// Reads the central directory, the end record is searched backwards to skip a trailing comment
+pub fn entries(data: &[u8]) -> Result<Vec<ZipEntry>> {
+    if data.len() < EOCD_SIZE {
+        return Err(invalid("too small"));
+    }
+
+    let eocd = (0..=data.len() - EOCD_SIZE).rev()
+        .take(EOCD_SIZE + u16::MAX as usize)
+        .find(|&pos| u32_at(data, pos).ok() == Some(EOCD_SIGNATURE))
+        .ok_or_else(|| invalid("end of central directory not found"))?;
+
+    let count = u16_at(data, eocd + 10)? as usize;
+    let mut pos = u32_at(data, eocd + 16)? as usize;
+
+    let mut entries = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        if u32_at(data, pos)? != CENTRAL_DIR_SIGNATURE {
+            return Err(invalid("bad central directory entry"));
+        }
+
+        let method = u16_at(data, pos + 10)?;
+        let compressed_size = u32_at(data, pos + 20)? as usize;
+        let uncompressed_size = u32_at(data, pos + 24)? as usize;
+        let name_len = u16_at(data, pos + 28)? as usize;
+        let extra_len = u16_at(data, pos + 30)? as usize;
+        let comment_len = u16_at(data, pos + 32)? as usize;
+        let header_offset = u32_at(data, pos + 42)? as usize;
+
+        let name = data.get(pos + CENTRAL_DIR_SIZE..pos + CENTRAL_DIR_SIZE + name_len).ok_or_else(|| invalid("truncated name"))?;
+
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            method,
+            compressed_size,
+            uncompressed_size,
+            header_offset
+        });
+
+        pos += CENTRAL_DIR_SIZE + name_len + extra_len + comment_len;
+    }
+
+    Ok(entries)
+}
+
+pub fn read(data: &[u8], entry: &ZipEntry) -> Result<Vec<u8>> {
+    let pos = entry.header_offset;
+
+    if u32_at(data, pos)? != LOCAL_HEADER_SIGNATURE {
+        return Err(invalid("bad local header"));
+    }
+
+    let name_len = u16_at(data, pos + 26)? as usize;
+    let extra_len = u16_at(data, pos + 28)? as usize;
+    let start = pos + LOCAL_HEADER_SIZE + name_len + extra_len;
+
+    let content = data.get(start..start + entry.compressed_size).ok_or_else(|| invalid("entry out of range"))?;
+
+    match entry.method {
+        METHOD_STORED => Ok(content.to_vec()),
+        METHOD_DEFLATED => deflate_decompress(content, entry.uncompressed_size).map_err(|e| match e {
+            Error::Decompression(reason) => invalid(&reason),
+            e => e,
+        }),
+        method => Err(invalid(&format!("unsupported compression method {}", method))),
+    }
+}